@@ -0,0 +1,191 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::http::Client;
+use chrono::{DateTime, Utc};
+use eyre::Context;
+use reqwest::{RequestBuilder, StatusCode};
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use url::Url;
+
+/// Response that **charted-server** sends back from its REST API.
+#[derive(Debug, Deserialize)]
+pub struct Response<T> {
+    /// Whether if the request was successful.
+    pub success: bool,
+
+    /// The data of the response, only avaliable if the request was successful.
+    #[serde(default = "Option::default")]
+    pub data: Option<T>,
+
+    /// A list of errors that occurred.
+    #[serde(default)]
+    pub errors: Vec<Error>,
+}
+
+/// An error that the REST API reported.
+#[derive(Debug, Deserialize, derive_more::Display)]
+#[display("{message} ({code})")]
+pub struct Error {
+    pub code: String,
+    pub message: String,
+}
+
+/// A session that was created from [`POST /users/login`].
+///
+/// [`POST /users/login`]: https://charts.noelware.org/docs/server/latest/api/reference/users#POST-/users/login
+#[derive(Debug, Deserialize)]
+pub struct Session {
+    #[serde(with = "crate::serde::secret_string")]
+    pub access_token: SecretString,
+}
+
+/// A user resource.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct User {
+    /// The user's username.
+    pub username: String,
+
+    /// The user's display name, if they set one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// An API key resource.
+#[derive(Debug, Deserialize)]
+pub struct ApiKey {
+    /// Name of the API key.
+    pub name: String,
+
+    /// Scopes that the API key is allowed to use.
+    #[serde(default)]
+    pub scopes: serde_json::Value,
+
+    /// Timestamp of when the API key expires, if it does.
+    #[serde(default, rename = "expires_in")]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// The token itself, only avaliable when the API key was created.
+    #[serde(default, deserialize_with = "deserialize_token")]
+    pub token: Option<SecretString>,
+}
+
+fn deserialize_token<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<SecretString>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.map(|token| SecretString::new(token.into())))
+}
+
+/// Error returned when the REST API reports a failed request.
+#[derive(Debug)]
+pub struct Failure {
+    pub url: Url,
+    pub status: StatusCode,
+    pub errors: Vec<Error>,
+}
+
+impl Failure {
+    /// Returns `true` if the registry rejected the credentials that were used.
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self.status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "request to {} failed with status {}", self.url, self.status)?;
+        if !self.errors.is_empty() {
+            write!(
+                f,
+                ": {}",
+                self.errors
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Joins `path` onto the `registry` URL, which includes the API version
+/// (i.e, `https://charts.noelware.org/api/v1`).
+pub fn endpoint(registry: &Url, path: &str) -> eyre::Result<Url> {
+    // `Url::join` will replace the last path segment if it doesn't end
+    // with a slash.
+    let mut base = registry.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+
+    base.join(path)
+        .with_context(|| format!("failed to join `{path}` onto registry {registry}"))
+}
+
+/// Sends `request` with `client` and returns the data from the REST API's response.
+pub async fn send<T: DeserializeOwned>(client: &Client, request: RequestBuilder) -> eyre::Result<T> {
+    let (url, status, body) = send_raw::<T>(client, request).await?;
+    match body.data {
+        Some(data) => Ok(data),
+        None => bail!("request to {url} ({status}) didn't return any data"),
+    }
+}
+
+/// Sends `request` with `client` and discards any data from the REST API's response.
+pub async fn send_empty(client: &Client, request: RequestBuilder) -> eyre::Result<()> {
+    send_raw::<serde::de::IgnoredAny>(client, request).await.map(|_| ())
+}
+
+async fn send_raw<T: DeserializeOwned>(
+    client: &Client,
+    request: RequestBuilder,
+) -> eyre::Result<(Url, StatusCode, Response<T>)> {
+    let response = client.send(request).await.context("failed to send request")?;
+    let url = response.url().clone();
+    let status = response.status();
+
+    let body = match response.json::<Response<T>>().await {
+        Ok(body) => body,
+
+        // the registry might not send back a JSON body if the request was rejected
+        // before it reached the REST API.
+        Err(_) if !status.is_success() => {
+            return Err(Failure {
+                url,
+                status,
+                errors: Vec::new(),
+            }
+            .into());
+        }
+
+        Err(e) => {
+            return Err(e).with_context(|| format!("failed to deserialize response from {url} ({status})"));
+        }
+    };
+
+    if body.success {
+        return Ok((url, status, body));
+    }
+
+    Err(Failure {
+        url,
+        status,
+        errors: body.errors,
+    }
+    .into())
+}
@@ -13,21 +13,36 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::http;
 use base64::{Engine, engine::general_purpose};
-use etcetera::{BaseStrategy, base_strategy::choose_native_strategy};
+use chrono::{DateTime, TimeDelta, Utc};
 use eyre::Context as _;
 use reqwest::{Url, header::HeaderValue};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
-    fs::{self, File, OpenOptions},
-    io::Write,
-    path::PathBuf,
+    env, fs,
+    path::{Path, PathBuf},
     str::FromStr,
     sync::Arc,
 };
 
+pub mod encryption;
+pub mod helper;
+pub mod layer;
+pub mod pin;
+use encryption::Encryption;
+use layer::{Layer, Source};
+use pin::Pin;
+
+/// Amount of days before an API key expires that a warning is emitted when the
+/// `auth.yaml` file is loaded.
+const EXPIRY_WARNING_DAYS: i64 = 7;
+
+/// Permissions that the `auth.yaml` file is created with, since it contains secrets.
+const AUTH_FILE_MODE: u32 = 0o600;
+
 /// A multithreaded-threaded initialized string used for contextual [`Auth`] instances.
 ///
 /// Originally, this was only a **Rc** but switched to **Arc** due to clap's
@@ -50,7 +65,7 @@ impl<'s> From<&'s str> for Context {
 
 /// A credential.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct Credential {
     /// A fully qualified HTTP URI that points to the registry to use.
     pub registry: Url,
@@ -62,6 +77,27 @@ pub struct Credential {
         skip_serializing_if = "Option::is_none"
     )]
     pub repr: Option<Repr>,
+
+    /// Name of the API key that was created by `helm charted login`, which is used
+    /// to revoke it when logging out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// Timestamp of when the API key expires. Expired credentials are removed when
+    /// the `auth.yaml` file is saved or with `helm charted context prune`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// HTTP settings that are used whenever this credential's registry is
+    /// contacted, which are merged with the `--connect-timeout` and
+    /// `--certificate` flags.
+    #[serde(default, skip_serializing_if = "http::Settings::is_empty")]
+    pub http: http::Settings,
+
+    /// Whether if tokens from a credential helper can be cached on disk, which is
+    /// only the case if the `auth.yaml` file it was loaded from isn't encrypted.
+    #[serde(skip)]
+    pub(crate) cache_tokens: bool,
 }
 
 /// Representation of what authentication scheme to use when requesting to
@@ -86,24 +122,184 @@ pub enum Repr {
     /// that the user created.
     #[serde(with = "crate::serde::secret_string")]
     ApiKey(SecretString),
+
+    /// Loads the API key from an external credential helper, see the [`helper`]
+    /// module for the protocol it needs to implement.
+    Helper {
+        command: String,
+
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+    },
 }
 
+impl Credential {
+    /// Creates a new [`Credential`] that points to a `registry`.
+    pub fn new(registry: Url, repr: Option<Repr>) -> Self {
+        Credential {
+            registry,
+            repr,
+            api_key: None,
+            expires_at: None,
+            http: http::Settings::default(),
+            cache_tokens: false,
+        }
+    }
+
+    /// Returns the time remaining until this credential's API key expires, or
+    /// `None` if it never expires.
+    pub fn remaining(&self) -> Option<TimeDelta> {
+        self.expires_at.map(|at| at - Utc::now())
+    }
+
+    /// Returns a human readable description of when this credential expires.
+    pub fn describe_expiry(&self) -> String {
+        match self.remaining() {
+            Some(remaining) if remaining <= TimeDelta::zero() => String::from("expired"),
+            Some(remaining) => format!("expires in {}", humanize(remaining)),
+            None => String::from("never expires"),
+        }
+    }
+
+    /// Returns the `Authorization` header value for this credential, or `None` if
+    /// no authentication is required.
+    pub fn authorization(&self) -> eyre::Result<Option<HeaderValue>> {
+        self.repr
+            .as_ref()
+            .map(|repr| repr.to_header_value(&self.registry, self.cache_tokens))
+            .transpose()
+    }
+
+    /// Returns the settings of this credential that shouldn't be accepted from a
+    /// source that isn't trusted: credential helpers run arbitrary commands, `env:`
+    /// sends any environment variable to the registry, and the `insecure` and
+    /// `proxy` settings change where and how securely the registry is contacted.
+    pub fn untrusted_settings(&self) -> Vec<&'static str> {
+        let mut settings = Vec::new();
+        match self.repr {
+            Some(Repr::Helper { .. }) => settings.push("a credential helper"),
+            Some(Repr::EnvironmentVariable(_)) => settings.push("an API key from an environment variable"),
+            _ => {}
+        }
+
+        if self.http.insecure {
+            settings.push("`insecure`");
+        }
+
+        if self.http.proxy.is_some() {
+            settings.push("`proxy`");
+        }
+
+        settings
+    }
+
+    /// Returns `true` if `url` has the same scheme, host and port as this
+    /// credential's registry.
+    pub fn same_origin(&self, url: &Url) -> bool {
+        self.registry.scheme() == url.scheme() &&
+            self.registry.host_str() == url.host_str() &&
+            self.registry.port_or_known_default() == url.port_or_known_default()
+    }
+
+    /// Returns `true` if `url` lives under this credential's registry.
+    pub fn matches(&self, url: &Url) -> bool {
+        if !self.same_origin(url) {
+            return false;
+        }
+
+        let prefix = self.registry.path().trim_end_matches('/');
+        match url.path().strip_prefix(prefix) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+/// Placeholder that secrets are replaced with when a [`Repr`] is displayed.
+pub const REDACTED: &str = "<redacted>";
+
 impl Repr {
-    /// Turns the represenation of the avaliable authentication scheme into a
-    /// [`HeaderValue`].
-    pub fn to_header_value(&self) -> eyre::Result<HeaderValue> {
+    /// Returns the kind of authentication scheme, which is the same as the prefix
+    /// that [`Repr`]'s [`FromStr`] impl accepts.
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Repr::EnvironmentVariable(_) => "env",
+            Repr::Basic { .. } => "basic",
+            Repr::ApiKey(_) => "apikey",
+            Repr::Helper { .. } => "helper",
+        }
+    }
+
+    /// Returns a copy of this representation with any secrets replaced by
+    /// [`REDACTED`].
+    pub fn redacted(&self) -> Repr {
+        match self {
+            Repr::Basic { username, .. } => Repr::Basic {
+                username: username.clone(),
+                password: SecretString::from(REDACTED),
+            },
+
+            Repr::ApiKey(_) => Repr::ApiKey(SecretString::from(REDACTED)),
+            repr => repr.clone(),
+        }
+    }
+
+    /// Returns `true` if the secrets of this representation were redacted by
+    /// [`Repr::redacted`].
+    pub fn is_redacted(&self) -> bool {
         match self {
-            Repr::EnvironmentVariable(value) => {
-                HeaderValue::from_str(&format!("ApiKey {value}")).context("failed to convert to header value")
+            Repr::Basic { password: secret, .. } | Repr::ApiKey(secret) => secret.expose_secret() == REDACTED,
+            _ => false,
+        }
+    }
+
+    /// Turns the represenation of the avaliable authentication scheme into a
+    /// [`HeaderValue`] for requests to `registry`. Tokens from a credential helper
+    /// are only cached on disk if `cache_tokens` is `true`.
+    pub fn to_header_value(&self, registry: &Url, cache_tokens: bool) -> eyre::Result<HeaderValue> {
+        let mut header = match self {
+            Repr::EnvironmentVariable(key) => {
+                let value = env::var(key).with_context(|| format!("failed to load API key from `${key}`"))?;
+                HeaderValue::from_str(&format!("ApiKey {value}")).context("failed to convert to header value")?
             }
 
             Repr::Basic { username, password } => {
                 let encoded = general_purpose::STANDARD.encode(format!("{username}:{}", password.expose_secret()));
-                HeaderValue::from_str(&encoded).context("failed to convert to header value")
+                HeaderValue::from_str(&format!("Basic {encoded}")).context("failed to convert to header value")?
             }
 
             Repr::ApiKey(value) => HeaderValue::from_str(&format!("ApiKey {}", value.expose_secret()))
-                .context("failed to convert to header value"),
+                .context("failed to convert to header value")?,
+
+            Repr::Helper { command, args } => {
+                let token = helper::token(command, args, registry, cache_tokens)?;
+                HeaderValue::from_str(&format!("ApiKey {}", token.expose_secret()))
+                    .context("failed to convert to header value")?
+            }
+        };
+
+        header.set_sensitive(true);
+        Ok(header)
+    }
+}
+
+/// Displays the representation in the syntax that [`Repr`]'s [`FromStr`] impl
+/// accepts, with any secrets replaced by [`REDACTED`]. Arguments of a credential
+/// helper that contain whitespace can't be parsed back from this syntax.
+impl std::fmt::Display for Repr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Repr::EnvironmentVariable(key) => write!(f, "env:{key}"),
+            Repr::Basic { username, .. } => write!(f, "basic:{username}:{REDACTED}"),
+            Repr::ApiKey(_) => write!(f, "apikey:{REDACTED}"),
+            Repr::Helper { command, args } => {
+                write!(f, "helper:{command}")?;
+                for arg in args {
+                    write!(f, " {arg}")?;
+                }
+
+                Ok(())
+            }
         }
     }
 }
@@ -117,7 +313,7 @@ pub enum ReprStrError {
     #[display("invalid syntax for `basic` directive: expected `username:password`")]
     InvalidSyntax,
 
-    #[display("unknown prefix or none given: expected `env:`, `apikey:`, or `basic:`")]
+    #[display("unknown prefix or none given: expected `env:`, `apikey:`, `basic:`, or `helper:`")]
     UnknownPrefix,
 
     #[display("contents was empty")]
@@ -142,6 +338,18 @@ impl FromStr for Repr {
             return Ok(Self::ApiKey(SecretString::new(Box::from(key))));
         }
 
+        if let Some(value) = s.strip_prefix("helper:") {
+            let mut words = value.split_whitespace().map(ToOwned::to_owned);
+            let Some(command) = words.next() else {
+                return Err(ReprStrError::Empty);
+            };
+
+            return Ok(Self::Helper {
+                command,
+                args: words.collect(),
+            });
+        }
+
         if let Some(value) = s.strip_prefix("basic:") {
             if let Some((username, password)) = value.split_once(':') {
                 if password.contains(':') {
@@ -161,107 +369,920 @@ impl FromStr for Repr {
     }
 }
 
-/// The schema of the `auth.yaml` in `$CONFIG_DIR/Noelware/charted-helm-plugin`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+/// The merged view of all the `auth.yaml` [layers](layer) that were loaded.
+#[derive(Debug)]
 pub struct Auth {
-    /// The context that is the **default** for the `helm charted context` subcommand.
+    /// The context that is used when none was given, which is the pinned context
+    /// if there is one. Use [`Auth::set_default`] to change the context that is
+    /// saved into the `auth.yaml` file.
     pub current: Context,
 
+    /// The current context from the `auth.yaml` files, which isn't affected by
+    /// the pinned context.
+    default: Context,
+
     /// A list of credentials avaliable.
-    #[serde(rename = "credential")]
     pub credentials: HashMap<Context, Credential>,
 
-    #[serde(skip)]
-    opened_file_from: PathBuf,
+    /// The layer that each context was loaded from.
+    origins: HashMap<Context, Layer>,
+
+    /// The files that were loaded, ordered from the lowest to highest precedence.
+    sources: Vec<Source>,
+
+    /// Whether if changes can't be saved, which is the case with `--no-create` or
+    /// when credentials were given through environment variables.
+    read_only: bool,
+
+    /// The context that was pinned for the working directory, which overrides
+    /// the current context from the files.
+    pin: Option<Pin>,
+
+    /// Whether if the pinned context was renamed, so the `.charted-context` file
+    /// has to be rewritten when saving.
+    repin: bool,
+
+    /// Locks on the files that can be written to, which are held until this is
+    /// dropped if it was loaded with [`Auth::load_for_update`].
+    locks: Vec<fs::File>,
 }
 
 impl Auth {
     /// Loads a `auth.yaml` file from a potential path (by `--auth-file` flag or the
     /// `CHARTED_HELM_AUTH_YAML` environment variable).
+    ///
+    /// If no path was given, then the system, user and project [layers](layer) are
+    /// merged together instead. If credentials were given through environment
+    /// variables (i.e, `CHARTED_HELM_REGISTRY`), then only those are used and the
+    /// filesystem is never touched.
+    ///
+    /// With `--no-create`, the user's `auth.yaml` file isn't created if it doesn't
+    /// exist and nothing can be saved.
     #[track_caller]
-    pub fn load<P: Into<Option<PathBuf>>>(potential: P) -> eyre::Result<Self> {
-        let path = Auth::get_potential_file(potential)?;
-        debug!(path = %path.display(), "loading `auth.yaml` file...");
+    pub fn load(args: &Args) -> eyre::Result<Self> {
+        Auth::load_with(args, false)
+    }
 
-        if !path.try_exists()? {
-            warn!(
-                "`auth.yaml` in path `{}` doesn't exist, creating new file...",
-                path.display()
-            );
+    /// Loads the `auth.yaml` files like [`Auth::load`], but holds a lock on each
+    /// file that can be written to until `self` is dropped, so that commands which
+    /// change contexts can't overwrite each other's changes.
+    #[track_caller]
+    pub fn load_for_update(args: &Args) -> eyre::Result<Self> {
+        Auth::load_with(args, true)
+    }
+
+    fn load_with(
+        Args {
+            file,
+            no_create,
+            trust_project_auth,
+        }: &Args,
+        lock: bool,
+    ) -> eyre::Result<Self> {
+        let mut sources = Vec::new();
+        let mut locks = Vec::new();
+        let mut read_only = *no_create;
+
+        match file {
+            Some(path) => {
+                if lock && !read_only {
+                    locks.push(crate::fs::lock(path)?);
+                }
 
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
+                sources.push(Auth::load_or_create(Layer::User, path.clone(), !read_only)?);
             }
 
-            let default = Auth {
-                current: Context::new("default"),
-                credentials: azalia::hashmap!(
-                    "default" => Credential {
-                        repr: None,
-                        registry: Url::parse("https://charts.noelware.org/api/v1").unwrap()
-                    }
-                ),
-                opened_file_from: path.clone(),
-            };
+            None => match layer::environment()? {
+                Some(file) => {
+                    debug!("using credentials from environment variables, `auth.yaml` files are ignored");
+
+                    read_only = true;
+                    sources.push(Source::new(Layer::Environment, PathBuf::new(), file));
+                }
 
-            let serialized = serde_yaml_ng::to_string(&default)?;
-            let mut file = File::create_new(&path)?;
+                None => {
+                    for layer in [Layer::System, Layer::User, Layer::Project] {
+                        let Some(path) = layer.path()? else {
+                            continue;
+                        };
 
-            write!(file, "{serialized}")?;
-            file.flush()?;
+                        let exists = path.try_exists()?;
+                        if layer == Layer::Project && exists && !trust_project_auth {
+                            warn!(
+                                "ignoring {} as the project layer isn't trusted, pass `--trust-project-auth` to load it",
+                                path.display()
+                            );
 
-            return Ok(default);
+                            continue;
+                        }
+
+                        if lock && !read_only && layer.is_writable() && (exists || layer == Layer::User) {
+                            locks.push(crate::fs::lock(&path)?);
+                        }
+
+                        if layer == Layer::User {
+                            sources.push(Auth::load_or_create(layer, path, !read_only)?);
+                        } else if exists {
+                            let source = Source::load(layer, path)?;
+                            if layer == Layer::Project {
+                                source.check_project()?;
+                            }
+
+                            sources.push(source);
+                        }
+                    }
+                }
+            },
         }
 
-        trace!("opening file `{}`", path.display());
+        let mut auth = Auth::merge(sources);
+        auth.read_only = read_only;
+        auth.locks = locks;
 
-        let file = File::open(&path)?;
-        let mut auth = serde_yaml_ng::from_reader::<_, Auth>(file)
-            .with_context(|| format!("failed to deserialize from file: {}", path.display()))?;
+        // credentials from environment variables are self-contained, so nothing
+        // from the working directory applies to them.
+        let pin = if auth.is_environment_only() {
+            None
+        } else {
+            Pin::discover(&env::current_dir()?)?
+        };
+
+        if let Some(pin) = pin {
+            if auth.credentials.contains_key(&pin.context) {
+                debug!(context = %pin.context, path = %pin.path.display(), "using pinned context");
+
+                auth.current = pin.context.clone();
+                auth.pin = Some(pin);
+            } else {
+                warn!(
+                    "context '{}' that was pinned by {} doesn't exist, ignoring it",
+                    pin.context,
+                    pin.path.display()
+                );
+            }
+        }
 
-        auth.opened_file_from = path;
+        auth.warn_expiry();
         Ok(auth)
     }
 
+    /// Merges the loaded files, from the lowest to the highest precedence.
+    fn merge(sources: Vec<Source>) -> Self {
+        let mut auth = Auth {
+            current: Context::new("default"),
+            default: Context::new("default"),
+            credentials: HashMap::new(),
+            origins: HashMap::new(),
+            sources,
+            read_only: false,
+            pin: None,
+            repin: false,
+            locks: Vec::new(),
+        };
+
+        for source in &auth.sources {
+            debug!(layer = %source.layer, path = %source.path.display(), "merging `auth.yaml` file");
+
+            if let Some(ref current) = source.file.current {
+                auth.current = current.clone();
+                auth.default = current.clone();
+            }
+
+            for (context, credential) in &source.file.credentials {
+                // tokens are never cached in plaintext for an encrypted `auth.yaml`,
+                // and nothing is written to disk for the environment layer.
+                let credential = Credential {
+                    cache_tokens: source.encryption.is_none() && source.layer != Layer::Environment,
+                    ..credential.clone()
+                };
+
+                auth.credentials.insert(context.clone(), credential);
+                auth.origins.insert(context.clone(), source.layer);
+            }
+        }
+
+        auth
+    }
+
+    fn load_or_create(layer: Layer, path: PathBuf, create: bool) -> eyre::Result<Source> {
+        debug!(path = %path.display(), "loading `auth.yaml` file...");
+        if path.try_exists()? {
+            return Source::load(layer, path);
+        }
+
+        let mut source = Source::new(layer, path, layer::File {
+            current: Some(Context::new("default")),
+            credentials: azalia::hashmap!(
+                "default" => Credential::new(Url::parse("https://charts.noelware.org/api/v1").unwrap(), None)
+            ),
+        });
+
+        if !create {
+            debug!(path = %source.path.display(), "`auth.yaml` doesn't exist, but `--no-create` was passed");
+            return Ok(source);
+        }
+
+        warn!(
+            "`auth.yaml` in path `{}` doesn't exist, creating new file...",
+            source.path.display()
+        );
+
+        source.save(AUTH_FILE_MODE)?;
+        Ok(source)
+    }
+
+    /// Warns about the credentials whose API key has expired or is close to
+    /// expiring. Nothing is removed, as loading never writes to the files.
+    fn warn_expiry(&self) {
+        for (context, credential) in &self.credentials {
+            match credential.remaining() {
+                Some(remaining) if remaining <= TimeDelta::zero() => {
+                    warn!(
+                        "api key for context '{}' has expired, use `helm charted login` to create a new one or `helm charted context prune` to remove it",
+                        context
+                    );
+                }
+
+                Some(remaining) if remaining <= TimeDelta::days(EXPIRY_WARNING_DAYS) => {
+                    warn!(
+                        "api key for context '{}' expires in {}, use `helm charted login` to create a new one",
+                        context,
+                        humanize(remaining)
+                    );
+                }
+
+                _ => {}
+            }
+        }
+    }
+
+    /// Removes all credentials whose API key has expired and returns their contexts.
+    pub fn prune(&mut self) -> Vec<Context> {
+        let mut expired = self
+            .credentials
+            .iter()
+            .filter(|(_, credential)| {
+                credential
+                    .remaining()
+                    .is_some_and(|remaining| remaining <= TimeDelta::zero())
+            })
+            .map(|(context, _)| context.clone())
+            .collect::<Vec<_>>();
+
+        expired.sort();
+        for context in &expired {
+            info!("api key for context '{}' has expired, removing it", context);
+            self.remove(context);
+        }
+
+        expired
+    }
+
     /// Commits changes from `self` within a closure into the `auth.yaml` file.
     pub fn commit(mut self, f: impl FnOnce(&mut Self)) -> eyre::Result<()> {
         f(&mut self);
         self.save()
     }
 
-    /// Flushes and saves the changes from `self` into the `auth.yaml` file.
-    pub fn save(&self) -> eyre::Result<()> {
-        debug!(path = %self.opened_file_from.display(), "saving and flushing changes to");
+    /// Flushes and saves the changes from `self` into the `auth.yaml` files.
+    ///
+    /// Each context is written back into the layer it was loaded from, new contexts
+    /// and contexts from the system layer that were changed are written into the
+    /// user layer. Only files that changed are written. Credentials that have
+    /// expired are [pruned](Auth::prune) first.
+    pub fn save(&mut self) -> eyre::Result<()> {
+        if self.read_only {
+            if self.sources.iter().any(|source| source.layer == Layer::Environment) {
+                bail!("credentials were loaded from environment variables, they can't be changed");
+            }
+
+            bail!("`--no-create` was passed, changes to `auth.yaml` can't be saved");
+        }
+
+        self.prune();
+
+        let write_layer = self.write_layer();
+        let mut targets = HashMap::<Layer, HashMap<Context, Credential>>::new();
+
+        for (context, credential) in &self.credentials {
+            let layer = match self.origins.get(context).copied() {
+                Some(Layer::System) if self.unchanged_in(Layer::System, context, credential) => continue,
+                Some(layer) if layer.is_writable() => layer,
+                _ => write_layer,
+            };
 
-        let mut file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&self.opened_file_from)?;
+            targets
+                .entry(layer)
+                .or_default()
+                .insert(context.clone(), credential.clone());
+        }
+
+        for (context, layer) in &self.origins {
+            if !layer.is_writable() && !self.credentials.contains_key(context) {
+                warn!(
+                    "context '{}' comes from the {} layer, which is never written to, it'll be loaded again",
+                    context, layer
+                );
+            }
+        }
 
-        let serialized = serde_yaml_ng::to_string(self)?;
+        // `current` is written into the highest layer that sets it, if it changed.
+        let current_layer = self
+            .sources
+            .iter()
+            .rev()
+            .find(|source| source.layer.is_writable() && source.file.current.is_some())
+            .map_or(write_layer, |source| source.layer);
 
-        write!(file, "{serialized}")?;
-        file.flush()?;
+        let merged_current = self
+            .sources
+            .iter()
+            .rev()
+            .find_map(|source| source.file.current.as_ref());
+
+        // the pinned context is never written back, only the default context is.
+        let current_changed = merged_current != Some(&self.default);
+
+        for source in self.sources.iter_mut().filter(|source| source.layer.is_writable()) {
+            let layer = source.layer;
+
+            // keep the contexts that are shadowed by a higher layer as-is.
+            source
+                .file
+                .credentials
+                .retain(|context, _| self.origins.get(context).is_some_and(|origin| *origin != layer));
+
+            source
+                .file
+                .credentials
+                .extend(targets.remove(&layer).unwrap_or_default());
+            if current_changed && layer == current_layer {
+                source.file.current = Some(self.default.clone());
+            }
+
+            source.save(AUTH_FILE_MODE)?;
+        }
+
+        if let Some(pin) = self.pin.as_ref().filter(|_| self.repin) {
+            info!("updating pinned context to '{}' in {}", pin.context, pin.path.display());
+
+            Pin::write(pin.path.parent().unwrap_or(Path::new(".")), &pin.context)?;
+            self.repin = false;
+        }
+
+        for (context, credential) in &self.credentials {
+            let origin = self.origins.get(context).copied();
+            if !matches!(origin, Some(Layer::System)) || !self.unchanged_in(Layer::System, context, credential) {
+                self.origins.insert(
+                    context.clone(),
+                    origin.filter(|layer| layer.is_writable()).unwrap_or(write_layer),
+                );
+            }
+        }
 
         Ok(())
     }
 
-    fn get_potential_file<P: Into<Option<PathBuf>>>(potential: P) -> eyre::Result<PathBuf> {
-        match potential.into() {
-            Some(path) => Ok(path),
-            None => {
-                let strategy = choose_native_strategy()?;
-                Ok(strategy.config_dir().join("Noelware/charted-helm-plugin/auth.yaml"))
+    /// Returns the layer that new contexts are written into.
+    fn write_layer(&self) -> Layer {
+        if self.sources.iter().any(|source| source.layer == Layer::User) {
+            Layer::User
+        } else {
+            self.sources
+                .iter()
+                .rev()
+                .find(|source| source.layer.is_writable())
+                .map_or(Layer::User, |source| source.layer)
+        }
+    }
+
+    /// Returns `true` if `credential` is the same as the one that `layer` has for
+    /// `context`.
+    fn unchanged_in(&self, layer: Layer, context: &Context, credential: &Credential) -> bool {
+        let Some(source) = self.sources.iter().find(|source| source.layer == layer) else {
+            return false;
+        };
+
+        source.file.credentials.get(context).is_some_and(|original| {
+            serde_yaml_ng::to_string(original).ok() == serde_yaml_ng::to_string(credential).ok()
+        })
+    }
+
+    /// Returns the layer and the file that a context was loaded from, or `None` if
+    /// it was created after the files were loaded.
+    pub fn origin(&self, context: &Context) -> Option<(Layer, &Path)> {
+        let layer = *self.origins.get(context)?;
+        self.sources
+            .iter()
+            .find(|source| source.layer == layer)
+            .map(|source| (layer, source.path.as_path()))
+    }
+
+    /// Returns the layers below a context's origin that also define it, which
+    /// it shadows.
+    pub fn shadowed(&self, context: &Context) -> Vec<Layer> {
+        let origin = self.origins.get(context);
+        self.sources
+            .iter()
+            .filter(|source| Some(&source.layer) != origin && source.file.credentials.contains_key(context))
+            .map(|source| source.layer)
+            .collect()
+    }
+
+    /// Returns `true` if the credentials were only given through environment
+    /// variables, in which case nothing should be read from or written to disk.
+    pub fn is_environment_only(&self) -> bool {
+        !self.sources.is_empty() && self.sources.iter().all(|source| source.layer == Layer::Environment)
+    }
+
+    /// Returns the context that was pinned for the working directory, if any.
+    pub fn pin(&self) -> Option<&Pin> {
+        self.pin.as_ref()
+    }
+
+    /// Returns the layers that were loaded and their files, from the lowest to
+    /// the highest precedence.
+    pub fn layers(&self) -> impl Iterator<Item = (Layer, &Path)> {
+        self.sources.iter().map(|source| (source.layer, source.path.as_path()))
+    }
+
+    fn user_source(&mut self) -> Option<&mut Source> {
+        let layer = self.write_layer();
+        self.sources.iter_mut().find(|source| source.layer == layer)
+    }
+
+    /// Returns how the user's `auth.yaml` file is encrypted at rest, if it is.
+    pub fn encryption(&self) -> Option<&Encryption> {
+        let layer = self.write_layer();
+        self.sources
+            .iter()
+            .find(|source| source.layer == layer)
+            .and_then(|source| source.encryption.as_ref())
+    }
+
+    /// Sets how the user's `auth.yaml` file should be encrypted at rest when it is
+    /// saved, or `None` to store it as plaintext.
+    pub fn set_encryption(&mut self, encryption: Option<Encryption>) {
+        if let Some(source) = self.user_source() {
+            source.encryption = encryption;
+            source.dirty = true;
+        }
+    }
+
+    /// Makes `context` the default context that is saved into the `auth.yaml` file.
+    /// A pinned context still overrides it in the working directory.
+    pub fn set_default(&mut self, context: Context) {
+        if self.pin.is_none() {
+            self.current = context.clone();
+        }
+
+        self.default = context;
+    }
+
+    /// Renames the context `from` into `to`, keeping it as the current, default or
+    /// pinned context if it was. Returns `false` if `from` doesn't exist.
+    pub fn rename(&mut self, from: &Context, to: Context) -> bool {
+        let Some(credential) = self.credentials.remove(from) else {
+            return false;
+        };
+
+        if self.current == *from {
+            self.current = to.clone();
+        }
+
+        if self.default == *from {
+            self.default = to.clone();
+        }
+
+        if let Some(pin) = self.pin.as_mut().filter(|pin| pin.context == *from) {
+            if pin.is_pin_file() {
+                pin.context = to.clone();
+                self.repin = true;
+            } else {
+                warn!(
+                    "context '{}' is pinned by the `global.context` setting in {}, change it to '{}'",
+                    from,
+                    pin.path.display(),
+                    to
+                );
             }
         }
+
+        // the renamed context stays in the same layer, unless it's read-only.
+        if let Some(layer) = self.origins.get(from).copied().filter(|layer| layer.is_writable()) {
+            self.origins.insert(to.clone(), layer);
+        }
+
+        self.credentials.insert(to, credential);
+        true
+    }
+
+    /// Removes a context from the list of credentials. If it was the default
+    /// context, then the default context is reassigned to `default` or the first
+    /// context that is avaliable. If it was pinned, then the default context is
+    /// used instead.
+    pub fn remove(&mut self, context: &Context) -> Option<Credential> {
+        let credential = self.credentials.remove(context)?;
+        if self.default == *context {
+            self.reassign_current();
+        }
+
+        if let Some(pin) = self.pin.take_if(|pin| pin.context == *context) {
+            warn!(
+                "context '{}' was pinned by {}, which should be updated or removed",
+                context,
+                pin.path.display()
+            );
+
+            self.current = self.default.clone();
+        }
+
+        Some(credential)
+    }
+
+    fn reassign_current(&mut self) {
+        let default = Context::new("default");
+        let next = if self.credentials.contains_key(&default) {
+            default
+        } else if let Some(context) = self.credentials.keys().min() {
+            context.clone()
+        } else {
+            // `current` must always point to an existing context, so we'll
+            // re-create the default one.
+            self.credentials.insert(
+                default.clone(),
+                Credential::new(Url::parse("https://charts.noelware.org/api/v1").unwrap(), None),
+            );
+
+            default
+        };
+
+        info!(
+            "current context was removed, switching from {} ~> {}",
+            self.default, next
+        );
+
+        if self.pin.is_none() {
+            self.current = next.clone();
+        }
+
+        self.default = next;
+    }
+
+    /// Returns the context and credential that should be used when requesting `url`.
+    ///
+    /// The credential whose registry is the longest prefix of `url` wins, otherwise
+    /// this will fall back to the [current](Auth::current) context if its registry
+    /// has the same [origin](Credential::same_origin). Credentials are never sent
+    /// to any other host.
+    pub fn credential_for(&self, url: &Url) -> Option<(&Context, &Credential)> {
+        self.credentials
+            .iter()
+            .filter(|(_, credential)| credential.matches(url))
+            .max_by_key(|(_, credential)| credential.registry.path().trim_end_matches('/').len())
+            .or_else(|| {
+                self.credentials
+                    .get_key_value(&self.current)
+                    .filter(|(_, credential)| credential.same_origin(url))
+            })
+    }
+}
+
+/// Formats a [`TimeDelta`] in a human readable form, i.e: `3d 4h`.
+pub fn humanize(delta: TimeDelta) -> String {
+    let (days, hours, minutes) = (delta.num_days(), delta.num_hours() % 24, delta.num_minutes() % 60);
+    match (days, hours) {
+        (0, 0) => format!("{minutes}m"),
+        (0, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
     }
 }
 
 #[derive(Debug, clap::Args)]
 #[group(id = "Authentication")]
 pub struct Args {
-    /// Location to an `auth.yaml` file that can load credentials.
+    /// Location to an `auth.yaml` file that can load credentials. If this isn't
+    /// set, then the system, user and project `auth.yaml` files are merged together.
     #[arg(short = 'c', long = "auth-file", env = "CHARTED_HELM_AUTH_YAML")]
     pub file: Option<PathBuf>,
+
+    /// Don't create the `auth.yaml` file if it doesn't exist. Nothing will be saved,
+    /// so commands that change contexts will fail.
+    #[arg(long, env = "CHARTED_HELM_NO_CREATE", default_value_t = false)]
+    pub no_create: bool,
+
+    /// Load the `.charted.auth.yaml` file that lives next to the closest `.charted.toml`
+    /// file. Only pass this for checkouts that you trust.
+    #[arg(long, env = "CHARTED_HELM_TRUST_PROJECT_AUTH", default_value_t = false)]
+    pub trust_project_auth: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Auth, Context, Credential, Repr};
+    use url::Url;
+
+    fn credential(registry: &str) -> Credential {
+        Credential::new(Url::parse(registry).unwrap(), None)
+    }
+
+    fn auth(current: &str, contexts: &[&str]) -> Auth {
+        Auth {
+            current: Context::new(current),
+            default: Context::new(current),
+            credentials: contexts
+                .iter()
+                .map(|context| (Context::new(context), credential("http://localhost:3651")))
+                .collect(),
+            origins: Default::default(),
+            sources: Vec::new(),
+            read_only: false,
+            pin: None,
+            repin: false,
+            locks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn credential_for_url() {
+        let auth = Auth {
+            current: Context::new("default"),
+            default: Context::new("default"),
+            credentials: azalia::hashmap!(
+                "default" => credential("https://charts.noelware.org/api/v1"),
+                "local" => credential("http://localhost:3651"),
+                "local-v1" => credential("http://localhost:3651/v1")
+            ),
+            origins: Default::default(),
+            sources: Vec::new(),
+            read_only: false,
+            pin: None,
+            repin: false,
+            locks: Vec::new(),
+        };
+
+        let (context, _) = auth
+            .credential_for(&Url::parse("http://localhost:3651/v1/indexes/noel").unwrap())
+            .unwrap();
+
+        assert_eq!(&Context::new("local-v1"), context);
+
+        let (context, _) = auth
+            .credential_for(&Url::parse("http://localhost:3651/v10/indexes/noel").unwrap())
+            .unwrap();
+
+        assert_eq!(&Context::new("local"), context);
+
+        let (context, _) = auth
+            .credential_for(&Url::parse("https://charts.noelware.org/api/v2/indexes/noel").unwrap())
+            .unwrap();
+
+        assert_eq!(&Context::new("default"), context);
+
+        // the current context's credentials are never sent to another host.
+        assert!(
+            auth.credential_for(&Url::parse("https://example.com/api/v1/indexes/noel").unwrap())
+                .is_none()
+        );
+        assert!(
+            auth.credential_for(&Url::parse("http://charts.noelware.org/api/v1/indexes/noel").unwrap())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn credential_keys_are_kebab_case() {
+        let mut credential = credential("http://localhost:3651");
+        credential.api_key = Some(String::from("helm"));
+        credential.expires_at = Some("2025-01-01T00:00:00Z".parse().unwrap());
+
+        let serialized = serde_yaml_ng::to_string(&credential).unwrap();
+        assert!(serialized.contains("\napi-key: helm\n"));
+        assert!(serialized.contains("\nexpires-at: 2025-01-01T00:00:00Z\n"));
+        assert!(serde_yaml_ng::from_str::<Credential>(&serialized).is_ok());
+    }
+
+    #[test]
+    fn repr_display_redacts_secrets() {
+        for (input, expected) in [
+            ("env:charted_token", "env:CHARTED_TOKEN"),
+            ("apikey:abcdef", "apikey:<redacted>"),
+            ("basic:noel:hunter2", "basic:noel:<redacted>"),
+            ("helper:charted-credential-op", "helper:charted-credential-op"),
+            ("helper:op  read --vault ci", "helper:op read --vault ci"),
+        ] {
+            assert_eq!(expected, input.parse::<Repr>().unwrap().to_string());
+        }
+
+        let Ok(Repr::Helper { command, args }) = "helper:op read --vault ci".parse::<Repr>() else {
+            panic!("expected a credential helper");
+        };
+
+        assert_eq!("op", command);
+        assert_eq!(vec!["read", "--vault", "ci"], args);
+        assert!("helper:".parse::<Repr>().is_err());
+    }
+
+    #[test]
+    fn layered_save() {
+        use super::layer::{Layer, Source};
+        use std::fs;
+
+        let dir = tempfile::tempdir().unwrap();
+        let (system, user, project) = (
+            dir.path().join("system.yaml"),
+            dir.path().join("user.yaml"),
+            dir.path().join("project.yaml"),
+        );
+
+        fs::write(
+            &system,
+            "credential:\n  default:\n    registry: https://charts.noelware.org/api/v1\n  corp:\n    registry: https://charts.corp.internal/api/v1\n",
+        )
+        .unwrap();
+
+        fs::write(
+            &user,
+            "current: default\ncredential:\n  default:\n    registry: https://charts.noelware.org/api/v1\n",
+        )
+        .unwrap();
+
+        fs::write(
+            &project,
+            "current: local\ncredential:\n  local:\n    registry: http://localhost:3651/api/v1\n",
+        )
+        .unwrap();
+
+        let mut auth = Auth::merge(vec![
+            Source::load(Layer::System, system.clone()).unwrap(),
+            Source::load(Layer::User, user.clone()).unwrap(),
+            Source::load(Layer::Project, project.clone()).unwrap(),
+        ]);
+
+        assert_eq!(Context::new("local"), auth.current);
+        assert_eq!(Some(Layer::System), auth.origin(&"corp".into()).map(|(layer, _)| layer));
+        assert_eq!(
+            Some(Layer::User),
+            auth.origin(&"default".into()).map(|(layer, _)| layer)
+        );
+        assert_eq!(vec![Layer::System], auth.shadowed(&"default".into()));
+
+        let system_before = fs::read_to_string(&system).unwrap();
+
+        auth.credentials.get_mut(&Context::new("corp")).unwrap().registry =
+            Url::parse("https://charts.corp.example/api/v1").unwrap();
+
+        auth.credentials
+            .insert("new".into(), credential("https://charts.example.com/api/v1"));
+
+        auth.remove(&"local".into());
+        auth.save().unwrap();
+
+        // the system layer is never written to, changes are written into the user layer.
+        assert_eq!(system_before, fs::read_to_string(&system).unwrap());
+
+        let user = fs::read_to_string(&user).unwrap();
+        assert!(user.contains("charts.corp.example"));
+        assert!(user.contains("charts.example.com"));
+
+        let project = fs::read_to_string(&project).unwrap();
+        assert!(!project.contains("localhost"));
+        assert!(project.starts_with("current: default"));
+    }
+
+    #[test]
+    fn pinned_save() {
+        use super::{
+            layer::{Layer, Source},
+            pin::Pin,
+        };
+        use std::fs;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("auth.yaml");
+        fs::write(
+            &path,
+            "current: prod\ncredential:\n  prod:\n    registry: https://charts.noelware.org/api/v1\n  staging:\n    registry: https://staging.noelware.org/api/v1\n  qa:\n    registry: https://qa.noelware.org/api/v1\n",
+        )
+        .unwrap();
+
+        let load = |pinned: &str| {
+            let mut auth = Auth::merge(vec![Source::load(Layer::User, path.clone()).unwrap()]);
+            let pin = Pin::write(dir.path(), &Context::new(pinned)).unwrap();
+
+            auth.current = pin.context.clone();
+            auth.pin = Some(pin);
+            auth
+        };
+
+        // renaming the pinned context rewrites the `.charted-context` file.
+        let mut auth = load("staging");
+        auth.rename(&"staging".into(), "stage".into());
+        auth.save().unwrap();
+
+        assert_eq!(
+            "stage\n",
+            fs::read_to_string(dir.path().join(".charted-context")).unwrap()
+        );
+        assert!(fs::read_to_string(&path).unwrap().starts_with("current: prod\n"));
+
+        // removing it falls back to the default context without changing it.
+        let mut auth = load("stage");
+        auth.remove(&"stage".into());
+        assert_eq!(Context::new("prod"), auth.current);
+        assert!(auth.pin().is_none());
+
+        auth.save().unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("current: prod\n"));
+
+        // the default context can still be changed explicitly.
+        let mut auth = load("qa");
+        auth.set_default("qa".into());
+        auth.save().unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("current: qa\n"));
+    }
+
+    #[test]
+    fn remove_current_context() {
+        let mut auth = auth("local", &["default", "local", "staging"]);
+
+        assert!(auth.remove(&"staging".into()).is_some());
+        assert_eq!(Context::new("local"), auth.current);
+
+        assert!(auth.remove(&"unknown".into()).is_none());
+        assert_eq!(Context::new("local"), auth.current);
+
+        assert!(auth.remove(&"local".into()).is_some());
+        assert_eq!(Context::new("default"), auth.current);
+        assert!(!auth.credentials.contains_key(&Context::new("local")));
+    }
+
+    #[test]
+    fn reassign_current() {
+        // without a `default` context, the first context by name is used.
+        let mut auth = auth("staging", &["staging", "production", "local"]);
+        auth.credentials.remove(&Context::new("staging"));
+        auth.reassign_current();
+        assert_eq!(Context::new("local"), auth.current);
+
+        // once every context is gone, `default` is re-created.
+        auth.credentials.clear();
+        auth.reassign_current();
+        assert_eq!(Context::new("default"), auth.current);
+        assert_eq!(
+            "https://charts.noelware.org/api/v1",
+            auth.credentials[&Context::new("default")].registry.as_str()
+        );
+    }
+
+    #[test]
+    fn load_for_update_holds_lock() {
+        use super::Args;
+        use std::fs::OpenOptions;
+
+        let dir = tempfile::tempdir().unwrap();
+        let args = Args {
+            file: Some(dir.path().join("auth.yaml")),
+            no_create: false,
+            trust_project_auth: false,
+        };
+
+        let try_lock = || {
+            let file = OpenOptions::new()
+                .write(true)
+                .open(dir.path().join("auth.yaml.lock"))
+                .unwrap();
+
+            fs4::FileExt::try_lock(&file).is_ok()
+        };
+
+        let auth = Auth::load_for_update(&args).unwrap();
+        assert!(!try_lock());
+
+        drop(auth);
+        assert!(try_lock());
+
+        // read-only commands never take the lock.
+        let _auth = Auth::load(&args).unwrap();
+        assert!(try_lock());
+    }
+
+    #[test]
+    fn environment_only() {
+        use super::layer::{File, Layer, Source};
+        use std::path::PathBuf;
+
+        let file = File {
+            current: Some("env".into()),
+            credentials: azalia::hashmap!("env" => credential("http://localhost:3651")),
+        };
+
+        let auth = Auth::merge(vec![Source::new(Layer::Environment, PathBuf::new(), file.clone())]);
+        assert!(auth.is_environment_only());
+        assert!(!auth.credentials[&Context::new("env")].cache_tokens);
+
+        let auth = Auth::merge(vec![Source::new(Layer::User, PathBuf::from("auth.yaml"), file)]);
+        assert!(!auth.is_environment_only());
+        assert!(auth.credentials[&Context::new("env")].cache_tokens);
+    }
 }
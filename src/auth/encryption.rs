@@ -0,0 +1,145 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use age::x25519;
+use eyre::Context;
+use secrecy::SecretString;
+use std::{env, fs, path::PathBuf, str::FromStr};
+
+/// Environment variable that contains the passphrase to unlock an encrypted
+/// `auth.yaml` file.
+pub const PASSPHRASE_ENV: &str = "CHARTED_HELM_AUTH_PASSPHRASE";
+
+/// Environment variable that points to an age identity file that can unlock an
+/// encrypted `auth.yaml` file.
+pub const IDENTITY_ENV: &str = "CHARTED_HELM_AUTH_IDENTITY";
+
+/// How an `auth.yaml` file is encrypted at rest.
+///
+/// Encrypted files are stored as ASCII-armored [age] files, so they can still be
+/// opened and decrypted by the `age` CLI.
+///
+/// [age]: https://age-encryption.org
+#[derive(Clone)]
+pub enum Encryption {
+    /// Encrypted with a passphrase.
+    Passphrase(SecretString),
+
+    /// Encrypted to an X25519 identity from an age identity file.
+    Identity { path: PathBuf, identity: x25519::Identity },
+}
+
+impl std::fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encryption::Passphrase(_) => f.write_str("Passphrase([REDACTED])"),
+            Encryption::Identity { path, .. } => f.debug_struct("Identity").field("path", path).finish_non_exhaustive(),
+        }
+    }
+}
+
+impl Encryption {
+    /// Returns `true` if `contents` looks like an age file.
+    pub fn is_encrypted(contents: &[u8]) -> bool {
+        let contents = contents.trim_ascii_start();
+        contents.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----") || contents.starts_with(b"age-encryption.org/")
+    }
+
+    /// Resolves how to unlock an encrypted `auth.yaml` file, which is either the
+    /// identity file in `$CHARTED_HELM_AUTH_IDENTITY`, the passphrase in
+    /// `$CHARTED_HELM_AUTH_PASSPHRASE` or a passphrase that is prompted for.
+    pub fn resolve() -> eyre::Result<Self> {
+        if let Some(path) = env::var_os(IDENTITY_ENV).filter(|path| !path.is_empty()) {
+            return Encryption::from_identity_file(PathBuf::from(path));
+        }
+
+        if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+            return Ok(Encryption::Passphrase(SecretString::new(passphrase.into())));
+        }
+
+        let passphrase = rpassword::prompt_password("Passphrase for `auth.yaml`: ")?;
+        Ok(Encryption::Passphrase(SecretString::new(passphrase.into())))
+    }
+
+    /// Loads the first X25519 identity from an age identity file.
+    pub fn from_identity_file(path: PathBuf) -> eyre::Result<Self> {
+        let contents =
+            fs::read_to_string(&path).with_context(|| format!("failed to read identity file: {}", path.display()))?;
+
+        let Some(line) = contents
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("AGE-SECRET-KEY-"))
+        else {
+            bail!("identity file {} doesn't contain an age identity", path.display());
+        };
+
+        let identity = x25519::Identity::from_str(line)
+            .map_err(|e| eyre!("failed to parse identity from identity file {}: {e}", path.display()))?;
+
+        Ok(Encryption::Identity { path, identity })
+    }
+
+    /// Encrypts `plaintext` into an ASCII-armored age file.
+    pub fn encrypt(&self, plaintext: &[u8]) -> eyre::Result<Vec<u8>> {
+        let encrypted = match self {
+            Encryption::Passphrase(passphrase) => {
+                age::encrypt_and_armor(&age::scrypt::Recipient::new(passphrase.clone()), plaintext)
+            }
+
+            Encryption::Identity { identity, .. } => age::encrypt_and_armor(&identity.to_public(), plaintext),
+        }
+        .context("failed to encrypt `auth.yaml`")?;
+
+        Ok(encrypted.into_bytes())
+    }
+
+    /// Decrypts an age file.
+    pub fn decrypt(&self, ciphertext: &[u8]) -> eyre::Result<Vec<u8>> {
+        match self {
+            Encryption::Passphrase(passphrase) => {
+                age::decrypt(&age::scrypt::Identity::new(passphrase.clone()), ciphertext)
+            }
+
+            Encryption::Identity { identity, .. } => age::decrypt(identity, ciphertext),
+        }
+        .context("failed to decrypt `auth.yaml`, is the passphrase or identity correct?")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encryption;
+    use age::x25519;
+
+    #[test]
+    fn roundtrip() {
+        let encryption = Encryption::Identity {
+            path: Default::default(),
+            identity: x25519::Identity::generate(),
+        };
+
+        let encrypted = encryption.encrypt(b"current: default").unwrap();
+        assert!(Encryption::is_encrypted(&encrypted));
+        assert_eq!(b"current: default".as_slice(), encryption.decrypt(&encrypted).unwrap());
+
+        let other = Encryption::Identity {
+            path: Default::default(),
+            identity: x25519::Identity::generate(),
+        };
+
+        assert!(other.decrypt(&encrypted).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for external credential helpers, similar to Docker's [credential helpers]
+//! or Git's `credential.helper`.
+//!
+//! A credential helper is an executable that receives the registry URL (followed
+//! by a newline) on stdin and writes a JSON object to stdout:
+//!
+//! ```json
+//! { "token": "<api key>", "expires_at": "2025-01-01T00:00:00Z" }
+//! ```
+//!
+//! `expires_at` (an RFC 3339 timestamp) or `expires_in` (in seconds) are optional.
+//! If either are given, the token is cached until it expires so the helper isn't
+//! invoked on every request. Otherwise, the helper is invoked every time a token
+//! is needed. Tokens are never cached for an encrypted `auth.yaml` file, as the
+//! cache is stored as plaintext.
+//!
+//! Anything the helper writes to stderr is passed through, so it can prompt the
+//! user if it needs to.
+//!
+//! [credential helpers]: https://github.com/docker/docker-credential-helpers
+
+use crate::cache::Cache;
+use chrono::{DateTime, TimeDelta, Utc};
+use eyre::Context;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+use url::Url;
+
+/// Cached tokens are treated as expired a bit earlier, so that they don't expire
+/// while a request is in flight.
+const EXPIRY_SKEW_SECS: i64 = 30;
+
+/// Output of a credential helper.
+#[derive(Deserialize)]
+struct Output {
+    #[serde(with = "crate::serde::secret_string")]
+    token: SecretString,
+
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+impl Output {
+    fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.expires_at.or_else(|| {
+            self.expires_in
+                .and_then(|secs| i64::try_from(secs).ok())
+                .and_then(TimeDelta::try_seconds)
+                .map(|delta| Utc::now() + delta)
+        })
+    }
+}
+
+/// A token that is cached in `$CACHE_DIR/helpers`.
+#[derive(Serialize, Deserialize)]
+struct Cached {
+    #[serde(with = "crate::serde::secret_string")]
+    token: SecretString,
+    expires_at: DateTime<Utc>,
+}
+
+/// Returns a token for `registry` from the credential helper, or the cached one
+/// if it hasn't expired yet. If `cache` is `false`, the helper is always invoked
+/// and its token is never written to disk.
+pub fn token(command: &str, args: &[String], registry: &Url, cache: bool) -> eyre::Result<SecretString> {
+    if !cache {
+        return run(command, args, registry).map(|output| output.token);
+    }
+
+    let path = cache_path(command, args, registry)?;
+    if let Some(cached) = read_cached(&path) {
+        trace!(helper = command, %registry, "using cached token from credential helper");
+        return Ok(cached.token);
+    }
+
+    let output = run(command, args, registry)?;
+    let Some(expires_at) = output.expires_at() else {
+        return Ok(output.token);
+    };
+
+    let cached = Cached {
+        token: output.token,
+        expires_at,
+    };
+
+    if let Err(e) = write_cached(&path, &cached) {
+        warn!(path = %path.display(), "failed to cache token from credential helper: {e}");
+    }
+
+    Ok(cached.token)
+}
+
+/// Invokes the credential helper and parses its output.
+fn run(command: &str, args: &[String], registry: &Url) -> eyre::Result<Output> {
+    debug!(helper = command, %registry, "invoking credential helper");
+
+    let mut child = Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("failed to run credential helper `{command}`"))?;
+
+    // `stdin` is dropped after writing, so that the helper sees EOF.
+    {
+        let mut stdin = child.stdin.take().expect("stdin to be piped");
+        writeln!(stdin, "{registry}")
+            .with_context(|| format!("failed to write registry to credential helper `{command}`"))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("failed to wait for credential helper `{command}`"))?;
+
+    if !output.status.success() {
+        bail!("credential helper `{command}` exited with {}", output.status);
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("credential helper `{command}` returned invalid output"))
+}
+
+fn cache_path(command: &str, args: &[String], registry: &Url) -> eyre::Result<PathBuf> {
+    let mut hasher = Sha256::new();
+    for part in std::iter::once(command)
+        .chain(args.iter().map(String::as_str))
+        .chain(std::iter::once(registry.as_str()))
+    {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+
+    let cache = Cache::open(None, false)?;
+    Ok(cache
+        .root()
+        .join("helpers")
+        .join(format!("{:x}.json", hasher.finalize())))
+}
+
+fn read_cached(path: &Path) -> Option<Cached> {
+    let contents = fs::read(path).ok()?;
+    let cached = serde_json::from_slice::<Cached>(&contents).ok()?;
+
+    (cached.expires_at - TimeDelta::seconds(EXPIRY_SKEW_SECS) > Utc::now()).then_some(cached)
+}
+
+fn write_cached(path: &Path, cached: &Cached) -> eyre::Result<()> {
+    // the token is a secret, so only the current user can read it.
+    crate::fs::write_atomic(path, &serde_json::to_vec(cached)?, Some(0o600))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{Cached, EXPIRY_SKEW_SECS, read_cached, write_cached};
+    use chrono::{TimeDelta, Utc};
+    use secrecy::{ExposeSecret, SecretString};
+    use std::{fs, os::unix::fs::PermissionsExt};
+    use url::Url;
+
+    #[test]
+    fn run_helper() {
+        let registry = Url::parse("https://charts.noelware.org/api/v1").unwrap();
+        let output = super::run(
+            "sh",
+            &[
+                String::from("-c"),
+                String::from(r#"read url; printf '{"token":"%s","expires_in":60}' "$url""#),
+            ],
+            &registry,
+        )
+        .unwrap();
+
+        assert_eq!("https://charts.noelware.org/api/v1", output.token.expose_secret());
+        assert!(output.expires_at().is_some());
+
+        assert!(super::run("sh", &[String::from("-c"), String::from("exit 1")], &registry).is_err());
+    }
+
+    #[test]
+    fn cached_tokens_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("helpers").join("token.json");
+        let cache = |secs| {
+            write_cached(&path, &Cached {
+                token: SecretString::from("weow"),
+                expires_at: Utc::now() + TimeDelta::seconds(secs),
+            })
+            .unwrap()
+        };
+
+        cache(EXPIRY_SKEW_SECS + 60);
+        assert_eq!("weow", read_cached(&path).unwrap().token.expose_secret());
+        assert_eq!(0o600, fs::metadata(&path).unwrap().permissions().mode() & 0o777);
+
+        // tokens that expire within the skew are treated as expired already.
+        cache(EXPIRY_SKEW_SECS - 5);
+        assert!(read_cached(&path).is_none());
+
+        cache(-60);
+        assert!(read_cached(&path).is_none());
+
+        fs::write(&path, "not json").unwrap();
+        assert!(read_cached(&path).is_none());
+    }
+}
@@ -0,0 +1,378 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Layers of `auth.yaml` files that are merged into a single [`Auth`](super::Auth)
+//! view.
+//!
+//! From the lowest to the highest precedence, the layers are:
+//!
+//! 1. **system**: `/etc/charted-helm-plugin/auth.yaml` (or
+//!    `%ProgramData%\Noelware\charted-helm-plugin\auth.yaml` on Windows), which can be
+//!    overridden with `$CHARTED_HELM_SYSTEM_AUTH_YAML`. This layer is managed by an
+//!    administrator and is never written to.
+//! 2. **user**: `$CONFIG_DIR/Noelware/charted-helm-plugin/auth.yaml`, which is created if
+//!    it doesn't exist.
+//! 3. **project**: `.charted.auth.yaml` that lives next to the closest `.charted.toml` or
+//!    `charted.toml` file, found by walking upwards from the current directory. As it
+//!    comes from a checkout that might not be trusted, this layer is only loaded with
+//!    `--trust-project-auth` and it can't use credential helpers, `env:` API keys or the
+//!    `insecure` and `proxy` settings.
+//!
+//! A context in a higher layer shadows a context with the same name in a lower
+//! layer.
+//!
+//! If credentials are given through environment variables, then none of the files
+//! are loaded and only the **environment** layer is used, see [`environment`].
+
+use super::{Context, Credential, Repr, encryption::Encryption};
+use etcetera::{BaseStrategy, base_strategy::choose_native_strategy};
+use eyre::Context as _;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, env, fs, path::PathBuf};
+use url::Url;
+
+/// Environment variable that overrides the location of the system layer.
+pub const SYSTEM_AUTH_ENV: &str = "CHARTED_HELM_SYSTEM_AUTH_YAML";
+
+/// Prefix of the environment variables that give credentials to the environment
+/// layer.
+const ENV_PREFIX: &str = "CHARTED_HELM_";
+
+/// Name of the project layer's file.
+pub const PROJECT_AUTH_FILE: &str = ".charted.auth.yaml";
+
+/// A layer of `auth.yaml` files, ordered from the lowest to the highest precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, derive_more::Display)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layer {
+    #[display("system")]
+    System,
+
+    #[display("user")]
+    User,
+
+    #[display("project")]
+    Project,
+
+    /// Credentials that were given through `CHARTED_HELM_*` environment variables,
+    /// which replace all the other layers.
+    #[display("environment")]
+    Environment,
+}
+
+impl Layer {
+    /// Returns `true` if the plugin is allowed to write to this layer.
+    pub const fn is_writable(self) -> bool {
+        !matches!(self, Layer::System | Layer::Environment)
+    }
+
+    /// Returns the location of this layer's file, or `None` if the layer doesn't
+    /// apply. This doesn't check if the file exists.
+    pub fn path(self) -> eyre::Result<Option<PathBuf>> {
+        match self {
+            Layer::System => {
+                if let Some(path) = env::var_os(SYSTEM_AUTH_ENV) {
+                    return Ok((!path.is_empty()).then(|| PathBuf::from(path)));
+                }
+
+                #[cfg(windows)]
+                return Ok(env::var_os("ProgramData")
+                    .map(|dir| PathBuf::from(dir).join("Noelware\\charted-helm-plugin\\auth.yaml")));
+
+                #[cfg(not(windows))]
+                Ok(Some(PathBuf::from("/etc/charted-helm-plugin/auth.yaml")))
+            }
+
+            Layer::User => {
+                let strategy = choose_native_strategy()?;
+                Ok(Some(
+                    strategy.config_dir().join("Noelware/charted-helm-plugin/auth.yaml"),
+                ))
+            }
+
+            Layer::Project => {
+                Ok(crate::config::find(&env::current_dir()?)?.map(|config| config.with_file_name(PROJECT_AUTH_FILE)))
+            }
+
+            Layer::Environment => Ok(None),
+        }
+    }
+}
+
+/// The schema of a single `auth.yaml` file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(super) struct File {
+    /// The context that is the **default**, only the highest layer that sets it wins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<Context>,
+
+    /// A list of credentials avaliable.
+    #[serde(default, rename = "credential")]
+    pub credentials: HashMap<Context, Credential>,
+}
+
+/// A `auth.yaml` file that was loaded into a layer.
+#[derive(Debug, Clone)]
+pub(super) struct Source {
+    pub layer: Layer,
+    pub path: PathBuf,
+    pub file: File,
+
+    /// How the file is encrypted at rest, if it is.
+    pub encryption: Option<Encryption>,
+
+    /// The file's serialized contents when it was loaded, so that only layers
+    /// that changed are written back.
+    pub loaded: String,
+
+    /// Whether the file needs to be written back even if its contents didn't
+    /// change, i.e. if its encryption changed.
+    pub dirty: bool,
+}
+
+impl Source {
+    /// Loads the file from `path` into `layer`.
+    pub fn load(layer: Layer, path: PathBuf) -> eyre::Result<Self> {
+        trace!(%layer, "opening file `{}`", path.display());
+
+        // the system layer has to be readable by every user, and the project layer
+        // is checked out with whatever permissions the repository has.
+        if layer == Layer::User {
+            crate::fs::warn_if_loose_permissions(&path);
+        }
+
+        let mut contents = fs::read(&path).with_context(|| format!("failed to read file: {}", path.display()))?;
+        let mut encryption = None;
+        if Encryption::is_encrypted(&contents) {
+            trace!("`auth.yaml` in path `{}` is encrypted, decrypting", path.display());
+
+            let unlock = Encryption::resolve()?;
+            contents = unlock.decrypt(&contents)?;
+            encryption = Some(unlock);
+        }
+
+        let file = serde_yaml_ng::from_slice::<File>(&contents)
+            .with_context(|| format!("failed to deserialize from file: {}", path.display()))?;
+
+        Ok(Source {
+            layer,
+            loaded: serde_yaml_ng::to_string(&file)?,
+            path,
+            file,
+            encryption,
+            dirty: false,
+        })
+    }
+
+    /// Rejects the [untrusted settings](Credential::untrusted_settings) that the
+    /// project layer isn't allowed to use.
+    pub fn check_project(&self) -> eyre::Result<()> {
+        for (context, credential) in &self.file.credentials {
+            if let Some(setting) = credential.untrusted_settings().first() {
+                bail!(
+                    "context '{}' in {} uses {}, which isn't allowed in the project layer",
+                    context,
+                    self.path.display(),
+                    setting
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates a source for a file that doesn't exist yet.
+    pub fn new(layer: Layer, path: PathBuf, file: File) -> Self {
+        Source {
+            layer,
+            path,
+            file,
+            encryption: None,
+            loaded: String::new(),
+            dirty: true,
+        }
+    }
+
+    /// Writes the file back if it changed since it was loaded.
+    pub fn save(&mut self, mode: u32) -> eyre::Result<()> {
+        let serialized = serde_yaml_ng::to_string(&self.file)?;
+        if !self.dirty && serialized == self.loaded {
+            trace!(layer = %self.layer, path = %self.path.display(), "file didn't change, skipping");
+            return Ok(());
+        }
+
+        debug!(layer = %self.layer, path = %self.path.display(), "saving and flushing changes to");
+
+        let mut contents = serialized.clone().into_bytes();
+        if let Some(encryption) = &self.encryption {
+            contents = encryption.encrypt(&contents)?;
+        }
+
+        crate::fs::write_atomic(&self.path, &contents, Some(mode))?;
+
+        self.loaded = serialized;
+        self.dirty = false;
+
+        Ok(())
+    }
+}
+
+/// Returns the credentials that were given through environment variables, or
+/// `None` if there are none.
+///
+/// The `CHARTED_HELM_REGISTRY` variable creates a context named after
+/// `CHARTED_HELM_CONTEXT` (or `env`), which is the current context. Any
+/// `CHARTED_HELM_REGISTRY_<NAME>` variable creates a context named `<name>` (in
+/// lowercase, with `_` replaced by `-`). Each context is authenticated by the
+/// `CHARTED_HELM_TOKEN[_<NAME>]` variable, which is never copied out of the
+/// environment, or by the `CHARTED_HELM_USERNAME[_<NAME>]` and
+/// `CHARTED_HELM_PASSWORD[_<NAME>]` variables.
+pub(super) fn environment() -> eyre::Result<Option<File>> {
+    environment_from(env::vars())
+}
+
+fn environment_from(vars: impl IntoIterator<Item = (String, String)>) -> eyre::Result<Option<File>> {
+    let vars = vars
+        .into_iter()
+        .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+        .collect::<HashMap<_, _>>();
+
+    let mut file = File::default();
+    for (key, registry) in &vars {
+        let Some(suffix) = key.strip_prefix("CHARTED_HELM_REGISTRY") else {
+            continue;
+        };
+
+        let context = match suffix.strip_prefix('_') {
+            Some(name) if !name.is_empty() => Context::new(name.to_ascii_lowercase().replace('_', "-")),
+            Some(_) => continue,
+            None if suffix.is_empty() => {
+                let context = Context::new(vars.get("CHARTED_HELM_CONTEXT").map_or("env", String::as_str));
+                file.current = Some(context.clone());
+
+                context
+            }
+
+            None => continue,
+        };
+
+        let registry = Url::parse(registry).with_context(|| format!("invalid registry url in `${key}`"))?;
+        let token = format!("CHARTED_HELM_TOKEN{suffix}");
+        let repr = if vars.contains_key(&token) {
+            Some(Repr::EnvironmentVariable(token))
+        } else {
+            match (
+                vars.get(&format!("CHARTED_HELM_USERNAME{suffix}")),
+                vars.get(&format!("CHARTED_HELM_PASSWORD{suffix}")),
+            ) {
+                (Some(username), Some(password)) => Some(Repr::Basic {
+                    username: username.clone(),
+                    password: SecretString::from(password.as_str()),
+                }),
+
+                (None, None) => None,
+                _ => bail!("both `CHARTED_HELM_USERNAME{suffix}` and `CHARTED_HELM_PASSWORD{suffix}` need to be set"),
+            }
+        };
+
+        file.credentials.insert(context, Credential::new(registry, repr));
+    }
+
+    if file.credentials.is_empty() {
+        return Ok(None);
+    }
+
+    if file.current.is_none() {
+        file.current = file.credentials.keys().min().cloned();
+    }
+
+    Ok(Some(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{File, Layer, Source, environment_from};
+    use crate::auth::{Context, Credential, Repr};
+    use std::path::PathBuf;
+
+    #[test]
+    fn credentials_from_environment() {
+        assert!(
+            environment_from([(String::from("HOME"), String::from("/root"))])
+                .unwrap()
+                .is_none()
+        );
+
+        let file = environment_from(
+            [
+                ("CHARTED_HELM_REGISTRY", "https://charts.noelware.org/api/v1"),
+                ("CHARTED_HELM_TOKEN", "abc"),
+                ("CHARTED_HELM_REGISTRY_STAGING_EU", "https://staging.example.com/api/v1"),
+                ("CHARTED_HELM_USERNAME_STAGING_EU", "noel"),
+                ("CHARTED_HELM_PASSWORD_STAGING_EU", "hunter2"),
+            ]
+            .map(|(key, value)| (key.to_owned(), value.to_owned())),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(Some(Context::new("env")), file.current);
+        assert!(matches!(
+            file.credentials[&Context::new("env")].repr,
+            Some(Repr::EnvironmentVariable(ref key)) if key == "CHARTED_HELM_TOKEN"
+        ));
+
+        assert!(matches!(
+            file.credentials[&Context::new("staging-eu")].repr,
+            Some(Repr::Basic { ref username, .. }) if username == "noel"
+        ));
+    }
+
+    #[test]
+    fn project_layer_restrictions() {
+        let source = |credential: Credential| {
+            Source::new(Layer::Project, PathBuf::from(".charted.auth.yaml"), File {
+                current: None,
+                credentials: azalia::hashmap!("local" => credential),
+            })
+        };
+
+        let registry = "http://localhost:3651".parse().unwrap();
+        let credential = Credential::new(registry, Some(Repr::ApiKey(String::from("weow").into())));
+        assert!(source(credential.clone()).check_project().is_ok());
+
+        let mut helper = credential.clone();
+        helper.repr = Some(Repr::Helper {
+            command: String::from("sh"),
+            args: Vec::new(),
+        });
+
+        assert!(source(helper).check_project().is_err());
+
+        let mut env = credential.clone();
+        env.repr = Some(Repr::EnvironmentVariable(String::from("AWS_SECRET_ACCESS_KEY")));
+        assert!(source(env).check_project().is_err());
+
+        let mut insecure = credential.clone();
+        insecure.http.insecure = true;
+        assert!(source(insecure).check_project().is_err());
+
+        let mut proxy = credential;
+        proxy.http.proxy = Some("http://localhost:3128".parse().unwrap());
+        assert!(source(proxy).check_project().is_err());
+    }
+}
@@ -0,0 +1,134 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-directory context pinning.
+//!
+//! A context can be pinned for a directory tree with a `.charted-context` file
+//! that contains the context's name, or with the `global.context` setting in a
+//! `.charted.toml` (or `charted.toml`) file. The closest file that is found by
+//! walking upwards from the working directory wins, and overrides the current
+//! context for every command that is run inside that tree.
+
+use super::Context;
+use eyre::Context as _;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Name of the file that pins a context.
+pub const PIN_FILE: &str = ".charted-context";
+
+/// A context that was pinned for the working directory.
+#[derive(Debug, Clone)]
+pub struct Pin {
+    /// The context that was pinned.
+    pub context: Context,
+
+    /// The file that pinned the context.
+    pub path: PathBuf,
+}
+
+impl Pin {
+    /// Returns `true` if the context was pinned by a `.charted-context` file,
+    /// which can be rewritten by `helm charted context switch`.
+    pub fn is_pin_file(&self) -> bool {
+        self.path.file_name().is_some_and(|name| name == PIN_FILE)
+    }
+
+    /// Discovers the pinned context by walking upwards from `start`.
+    pub fn discover(start: &Path) -> eyre::Result<Option<Pin>> {
+        for dir in start.ancestors() {
+            let path = dir.join(PIN_FILE);
+            if path.try_exists()? {
+                let contents =
+                    fs::read_to_string(&path).with_context(|| format!("failed to read file: {}", path.display()))?;
+
+                let Some(name) = contents.lines().map(str::trim).find(|line| !line.is_empty()) else {
+                    bail!("{} is empty, expected the name of a context", path.display());
+                };
+
+                return Ok(Some(Pin {
+                    context: Context::new(name),
+                    path,
+                }));
+            }
+
+            if let Some(path) = crate::config::find_in(dir)? {
+                #[derive(Deserialize)]
+                struct Config {
+                    #[serde(default)]
+                    global: Global,
+                }
+
+                #[derive(Default, Deserialize)]
+                struct Global {
+                    #[serde(default)]
+                    context: Option<Context>,
+                }
+
+                let contents =
+                    fs::read_to_string(&path).with_context(|| format!("failed to read file: {}", path.display()))?;
+
+                let config = toml::from_str::<Config>(&contents)
+                    .with_context(|| format!("failed to deserialize from file: {}", path.display()))?;
+
+                if let Some(context) = config.global.context {
+                    return Ok(Some(Pin { context, path }));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Writes a `.charted-context` file into `dir` that pins `context`.
+    pub fn write(dir: &Path, context: &Context) -> eyre::Result<Pin> {
+        let path = dir.join(PIN_FILE);
+        crate::fs::write_atomic(&path, format!("{context}\n").as_bytes(), None)?;
+
+        Ok(Pin {
+            context: context.clone(),
+            path,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pin;
+    use crate::auth::Context;
+    use std::fs;
+
+    #[test]
+    fn discover_upwards() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("charts/my-chart");
+        fs::create_dir_all(&nested).unwrap();
+
+        assert!(Pin::discover(&nested).unwrap().is_none());
+
+        fs::write(dir.path().join(".charted.toml"), "[global]\ncontext = \"staging\"\n").unwrap();
+        let pin = Pin::discover(&nested).unwrap().unwrap();
+        assert_eq!(Context::new("staging"), pin.context);
+        assert!(!pin.is_pin_file());
+
+        Pin::write(&dir.path().join("charts"), &Context::new("local")).unwrap();
+        let pin = Pin::discover(&nested).unwrap().unwrap();
+        assert_eq!(Context::new("local"), pin.context);
+        assert!(pin.is_pin_file());
+    }
+}
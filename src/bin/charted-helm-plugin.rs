@@ -13,6 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use async_compat::Compat;
 use charted_helm_plugin::Program;
 use clap::Parser;
 use std::{env, path::PathBuf};
@@ -44,9 +45,12 @@ fn main() -> eyre::Result<()> {
     let program = Program::parse();
     program.init_logging();
 
+    let globals = program.globals();
     if let Some(dir) = program.workdir {
         std::env::set_current_dir(dir)?;
     }
 
-    smol::block_on(program.command.run())
+    // `reqwest` requires a Tokio runtime to be avaliable, so `Compat` will
+    // provide one while the future is being polled by `smol`.
+    smol::block_on(Compat::new(program.command.run(globals)))
 }
@@ -0,0 +1,513 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::http::Client;
+use etcetera::{BaseStrategy, base_strategy::choose_native_strategy};
+use eyre::Context;
+use reqwest::{
+    RequestBuilder, StatusCode,
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+/// Metadata about a cached `index.yaml` that is used to revalidate it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Metadata {
+    url: Url,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+}
+
+/// The subset of an `index.yaml` that is required to resolve chart digests.
+#[derive(Debug, Deserialize)]
+struct Index {
+    #[serde(default)]
+    entries: HashMap<String, Vec<IndexEntry>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexEntry {
+    version: String,
+
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+/// On-disk cache for `index.yaml` files and chart tarballs that are served by
+/// **charted-server**.
+///
+/// `index.yaml` files are keyed by the URL they were requested from and are
+/// revalidated with the `ETag` and `Last-Modified` headers that the registry
+/// sent back. Chart tarballs are addressed by their SHA-256 digest and are never
+/// fetched again once they are in the cache.
+///
+/// When the cache is in **offline** mode, requests are never sent and a
+/// [`MissingError`] is returned for the first resource that isn't in the cache.
+///
+/// A [disabled](Cache::disabled) cache always sends requests and never touches
+/// the filesystem.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    root: PathBuf,
+    offline: bool,
+    persist: bool,
+}
+
+/// Error returned when the [`Cache`] is in offline mode and a resource that was
+/// requested is not in the cache.
+#[derive(Debug)]
+pub struct MissingError {
+    root: PathBuf,
+    missing: String,
+}
+
+impl std::fmt::Display for MissingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "running in offline mode, but {} is missing from the cache in {}",
+            self.missing,
+            self.root.display()
+        )
+    }
+}
+
+impl std::error::Error for MissingError {}
+
+impl Cache {
+    /// Opens the cache in a potential directory. If `None` is given, this will be
+    /// `$HELM_CACHE_HOME/charted` or the cache directory next to where the
+    /// `auth.yaml` file lives in.
+    pub fn open<P: Into<Option<PathBuf>>>(potential: P, offline: bool) -> eyre::Result<Self> {
+        let root = Cache::get_potential_dir(potential)?;
+        debug!(root = %root.display(), offline, "using cache directory");
+
+        Ok(Cache {
+            root,
+            offline,
+            persist: true,
+        })
+    }
+
+    /// Returns a cache that never reads or writes anything, which is used when
+    /// nothing should be written to disk.
+    pub fn disabled() -> Self {
+        debug!("cache is disabled");
+
+        Cache {
+            root: PathBuf::new(),
+            offline: false,
+            persist: false,
+        }
+    }
+
+    /// Returns the directory where this cache lives in.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the `index.yaml` that lives in `url`.
+    ///
+    /// If it was cached before, then the request will be sent with the
+    /// `If-None-Match` and `If-Modified-Since` headers and the cached copy is
+    /// returned if the registry replies with **304 Not Modified**.
+    pub async fn index(&self, client: &Client, url: &Url, mut request: RequestBuilder) -> eyre::Result<Vec<u8>> {
+        let (path, metadata_path) = self.index_paths(url);
+        let cached = match self.persist.then(|| (fs::read(&path), fs::read(&metadata_path))) {
+            Some((Ok(contents), Ok(metadata))) => match serde_json::from_slice::<Metadata>(&metadata) {
+                Ok(metadata) => Some((contents, metadata)),
+                Err(e) => {
+                    warn!(error = %e, path = %metadata_path.display(), "ignoring corrupted cache metadata");
+                    None
+                }
+            },
+
+            _ => None,
+        };
+
+        if self.offline {
+            let Some((contents, _)) = cached else {
+                return Err(self.missing(format!("`index.yaml` from {url}")).into());
+            };
+
+            debug!(%url, "using cached `index.yaml` in offline mode");
+            return Ok(contents);
+        }
+
+        if let Some((_, ref metadata)) = cached {
+            if let Some(ref etag) = metadata.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+
+            if let Some(ref last_modified) = metadata.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = client
+            .send(request)
+            .await
+            .with_context(|| format!("failed to send request to {url}"))?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some((contents, _)) = cached {
+                debug!(%url, "cached `index.yaml` is still fresh");
+                return Ok(contents);
+            }
+        }
+
+        let response = response
+            .error_for_status()
+            .with_context(|| format!("registry failed to serve {url}"))?;
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+                .map(String::from)
+        };
+
+        let metadata = Metadata {
+            url: url.clone(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+
+        let contents = response.bytes().await?.to_vec();
+
+        // the contents are written first, so that metadata never refers to an
+        // `index.yaml` that isn't on disk yet.
+        if self.persist {
+            debug!(%url, path = %path.display(), "caching `index.yaml`");
+            if self.write(&path, &contents) {
+                self.write(&metadata_path, &serde_json::to_vec(&metadata)?);
+            }
+        }
+
+        Ok(contents)
+    }
+
+    /// Returns a chart tarball that lives in `url` by its SHA-256 `digest`.
+    ///
+    /// If the tarball is in the cache, then `request` is never sent. If the digest
+    /// is unknown, then the tarball is always fetched and is cached by the digest
+    /// of its contents.
+    pub async fn chart(
+        &self,
+        client: &Client,
+        url: &Url,
+        digest: Option<&str>,
+        request: RequestBuilder,
+    ) -> eyre::Result<Vec<u8>> {
+        let digest = digest.map(|digest| digest.strip_prefix("sha256:").unwrap_or(digest).to_ascii_lowercase());
+        if let Some(digest) = digest.as_deref().filter(|_| self.persist) {
+            let path = self.chart_path(digest);
+            if let Ok(contents) = fs::read(&path) {
+                if sha256(&contents) == digest {
+                    debug!(%digest, "using cached chart tarball");
+                    return Ok(contents);
+                }
+
+                warn!(%digest, path = %path.display(), "cached chart tarball doesn't match its digest, fetching again");
+            }
+        }
+
+        if self.offline {
+            return Err(match digest {
+                Some(digest) => self.missing(format!("chart tarball from {url} (digest {digest})")),
+                None => self.missing(format!(
+                    "chart tarball from {url} (digest is unknown as its `index.yaml` is not cached)"
+                )),
+            }
+            .into());
+        }
+
+        let response = client
+            .send(request)
+            .await
+            .with_context(|| format!("failed to send request to {url}"))?
+            .error_for_status()
+            .with_context(|| format!("registry failed to serve {url}"))?;
+
+        let contents = response.bytes().await?.to_vec();
+        let actual = sha256(&contents);
+
+        if let Some(digest) = digest {
+            if digest != actual {
+                bail!("chart tarball from {url} has digest {actual}, but expected {digest}");
+            }
+        }
+
+        if self.persist {
+            let path = self.chart_path(&actual);
+            debug!(%url, path = %path.display(), "caching chart tarball");
+            self.write(&path, &contents);
+        }
+
+        Ok(contents)
+    }
+
+    /// Looks up the digest of a chart's `version` from the cached `index.yaml`
+    /// that lives in `index`.
+    pub fn digest_of(&self, index: &Url, chart: &str, version: &str) -> Option<String> {
+        if !self.persist {
+            return None;
+        }
+
+        let (path, _) = self.index_paths(index);
+        let contents = fs::read(path).ok()?;
+        let index = serde_yaml_ng::from_slice::<Index>(&contents).ok()?;
+
+        index
+            .entries
+            .get(chart)?
+            .iter()
+            .find(|entry| entry.version == version)?
+            .digest
+            .clone()
+    }
+
+    fn missing(&self, resource: String) -> MissingError {
+        MissingError {
+            root: self.root.clone(),
+            missing: resource,
+        }
+    }
+
+    fn index_paths(&self, url: &Url) -> (PathBuf, PathBuf) {
+        let key = sha256(url.as_str().as_bytes());
+        let dir = self.root.join("indexes");
+
+        (dir.join(format!("{key}.yaml")), dir.join(format!("{key}.json")))
+    }
+
+    fn chart_path(&self, digest: &str) -> PathBuf {
+        self.root.join("charts").join(format!("{digest}.tgz"))
+    }
+
+    /// Writes a cache entry and returns `true` if it was written. A cache that
+    /// can't be written to (i.e, it's read-only or the disk is full) only means
+    /// that the resource is downloaded again next time.
+    fn write(&self, path: &Path, contents: &[u8]) -> bool {
+        match crate::fs::write_atomic(path, contents, None) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(error = %e, path = %path.display(), "failed to write cache entry");
+                false
+            }
+        }
+    }
+
+    fn get_potential_dir<P: Into<Option<PathBuf>>>(potential: P) -> eyre::Result<PathBuf> {
+        if let Some(path) = potential.into() {
+            return Ok(path);
+        }
+
+        if let Some(home) = env::var_os("HELM_CACHE_HOME").filter(|home| !home.is_empty()) {
+            return Ok(PathBuf::from(home).join("charted"));
+        }
+
+        let strategy = choose_native_strategy()?;
+        Ok(strategy.cache_dir().join("Noelware/charted-helm-plugin"))
+    }
+}
+
+fn sha256(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cache, MissingError, sha256};
+    use crate::http::{Client, Retry, testing::Server};
+
+    fn client() -> Client {
+        Client::new(reqwest::Client::new(), Retry::default())
+    }
+
+    #[test]
+    fn index_is_revalidated() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open(dir.path().to_path_buf(), false).unwrap();
+        let server = Server::serve(&[
+            "200 OK\r\netag: \"v1\"\r\nlast-modified: Wed, 21 Oct 2015 07:28:00 GMT\r\n\r\nentries: {}\n",
+            "304 Not Modified",
+        ]);
+
+        let client = client();
+        let url = server.url.join("/index.yaml").unwrap();
+        for _ in 0..2 {
+            let contents = smol::block_on(async_compat::Compat::new(cache.index(
+                &client,
+                &url,
+                client.get(url.clone()),
+            )))
+            .unwrap();
+
+            assert_eq!(b"entries: {}\n", contents.as_slice());
+        }
+
+        let requests = server.requests();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\"\r\n"));
+        assert!(requests[1].contains("if-modified-since: wed, 21 oct 2015 07:28:00 gmt\r\n"));
+    }
+
+    #[test]
+    fn charts_are_keyed_by_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open(dir.path().to_path_buf(), false).unwrap();
+        let digest = sha256(b"tarball");
+        let index = format!("entries:\n  hello:\n  - version: 0.1.0\n    digest: {digest}\n");
+        let server = Server::serve(&[&format!("200 OK\r\n\r\n{index}"), "200 OK\r\n\r\ntarball"]);
+
+        let client = client();
+        let url = server.url.join("/index.yaml").unwrap();
+        smol::block_on(async_compat::Compat::new(cache.index(
+            &client,
+            &url,
+            client.get(url.clone()),
+        )))
+        .unwrap();
+
+        let found = cache.digest_of(&url, "hello", "0.1.0").unwrap();
+        assert_eq!(digest, found);
+        assert!(cache.digest_of(&url, "hello", "0.2.0").is_none());
+
+        // the second request is only ever sent once, as the tarball is served
+        // from the cache afterwards.
+        let tarball = server.url.join("/hello-0.1.0.tgz").unwrap();
+        for _ in 0..2 {
+            let contents = smol::block_on(async_compat::Compat::new(cache.chart(
+                &client,
+                &tarball,
+                Some(&format!("sha256:{found}")),
+                client.get(tarball.clone()),
+            )))
+            .unwrap();
+
+            assert_eq!(b"tarball", contents.as_slice());
+        }
+
+        assert_eq!(2, server.requests().len());
+    }
+
+    #[test]
+    fn offline_mode_only_uses_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = Server::serve(&["200 OK\r\n\r\nentries: {}\n"]);
+
+        let client = client();
+        let url = server.url.join("/index.yaml").unwrap();
+        let cache = Cache::open(dir.path().to_path_buf(), false).unwrap();
+        smol::block_on(async_compat::Compat::new(cache.index(
+            &client,
+            &url,
+            client.get(url.clone()),
+        )))
+        .unwrap();
+
+        let cache = Cache::open(dir.path().to_path_buf(), true).unwrap();
+        let contents = smol::block_on(async_compat::Compat::new(cache.index(
+            &client,
+            &url,
+            client.get(url.clone()),
+        )))
+        .unwrap();
+
+        assert_eq!(b"entries: {}\n", contents.as_slice());
+
+        let other = server.url.join("/other/index.yaml").unwrap();
+        let error = smol::block_on(async_compat::Compat::new(cache.index(
+            &client,
+            &other,
+            client.get(other.clone()),
+        )))
+        .unwrap_err();
+
+        let error = error.downcast_ref::<MissingError>().unwrap();
+        assert!(error.to_string().contains(&format!("`index.yaml` from {other}")));
+        assert_eq!(1, server.requests().len());
+    }
+
+    #[test]
+    fn unwritable_cache_still_returns_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("cache");
+        std::fs::write(&root, "not a directory").unwrap();
+
+        let server = Server::serve(&["200 OK\r\n\r\nentries: {}\n", "200 OK\r\n\r\ntarball"]);
+        let client = client();
+        let cache = Cache::open(root, false).unwrap();
+
+        let url = server.url.join("/index.yaml").unwrap();
+        let contents = smol::block_on(async_compat::Compat::new(cache.index(
+            &client,
+            &url,
+            client.get(url.clone()),
+        )))
+        .unwrap();
+
+        assert_eq!(b"entries: {}\n", contents.as_slice());
+
+        let tarball = server.url.join("/hello-0.1.0.tgz").unwrap();
+        let contents = smol::block_on(async_compat::Compat::new(cache.chart(
+            &client,
+            &tarball,
+            None,
+            client.get(tarball.clone()),
+        )))
+        .unwrap();
+
+        assert_eq!(b"tarball", contents.as_slice());
+    }
+
+    #[test]
+    fn disabled_cache_always_fetches() {
+        let server = Server::serve(&[
+            "200 OK\r\netag: \"v1\"\r\n\r\nentries: {}\n",
+            "200 OK\r\netag: \"v1\"\r\n\r\nentries: {}\n",
+        ]);
+
+        let client = client();
+        let cache = Cache::disabled();
+        let url = server.url.join("/index.yaml").unwrap();
+        for _ in 0..2 {
+            smol::block_on(async_compat::Compat::new(cache.index(
+                &client,
+                &url,
+                client.get(url.clone()),
+            )))
+            .unwrap();
+        }
+
+        let requests = server.requests();
+        assert_eq!(2, requests.len());
+        assert!(!requests[1].contains("if-none-match"));
+        assert!(cache.digest_of(&url, "hello", "0.1.0").is_none());
+    }
+}
@@ -13,6 +13,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::Globals;
+
 mod completions;
 mod context;
 mod download;
@@ -20,6 +22,7 @@ mod init;
 mod login;
 mod logout;
 mod repository;
+mod whoami;
 
 #[derive(Debug, clap::Subcommand)]
 pub enum Subcommand {
@@ -27,6 +30,7 @@ pub enum Subcommand {
     Init(init::Args),
     Login(login::Args),
     Logout(logout::Args),
+    Whoami(whoami::Args),
 
     #[command(subcommand)]
     Context(context::Subcmd),
@@ -39,12 +43,15 @@ pub enum Subcommand {
 }
 
 impl Subcommand {
-    pub async fn run(self) -> eyre::Result<()> {
+    pub async fn run(self, globals: Globals) -> eyre::Result<()> {
         match self {
             Subcommand::Completions(args) => completions::run(args),
-            Subcommand::Download(args) => download::run(args).await,
-            Subcommand::Context(cmd) => cmd.run(),
+            Subcommand::Download(args) => download::run(args, globals).await,
+            Subcommand::Context(cmd) => cmd.run(globals).await,
+            Subcommand::Login(args) => login::run(args, globals).await,
+            Subcommand::Logout(args) => logout::run(args, globals).await,
             Subcommand::Repository(cmd) => cmd.run().await,
+            Subcommand::Whoami(args) => whoami::run(args, globals).await,
             _ => todo!(),
         }
     }
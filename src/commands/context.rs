@@ -13,25 +13,55 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::Globals;
+
 mod add;
+mod decrypt;
 mod delete;
+mod encrypt;
+mod explain;
 mod export;
+mod helm;
+mod import;
 mod list;
+mod prune;
+mod rename;
+mod show;
 mod switch;
 
 /// Subcommands for dealing with authentication contexts.
 #[derive(Debug, clap::Subcommand)]
 pub enum Subcmd {
     Add(add::Args),
+    Decrypt(decrypt::Args),
+    Delete(delete::Args),
+    Encrypt(encrypt::Args),
+    Explain(explain::Args),
     Export(export::Args),
+    Import(import::Args),
+    ImportHelm(helm::Args),
+    List(list::Args),
+    Prune(prune::Args),
+    Rename(rename::Args),
+    Show(show::Args),
     Switch(switch::Args),
 }
 
 impl Subcmd {
-    pub fn run(self) -> eyre::Result<()> {
+    pub async fn run(self, globals: Globals) -> eyre::Result<()> {
         match self {
             Self::Add(args) => add::run(args),
+            Self::Decrypt(args) => decrypt::run(args),
+            Self::Delete(args) => delete::run(args),
+            Self::Encrypt(args) => encrypt::run(args),
+            Self::Explain(args) => explain::run(args),
             Self::Export(args) => export::run(args),
+            Self::Import(args) => import::run(args),
+            Self::ImportHelm(args) => helm::run(args, globals).await,
+            Self::List(args) => list::run(args),
+            Self::Prune(args) => prune::run(args),
+            Self::Rename(args) => rename::run(args),
+            Self::Show(args) => show::run(args),
             Self::Switch(args) => switch::run(args),
         }
     }
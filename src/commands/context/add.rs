@@ -35,6 +35,9 @@ pub struct Args {
     /// - `apikey:<key>`: uses a generated API key by the server.
     /// - `basic:<username>:<password>`: uses Basic authentication, do note that the
     ///   server might reject the request if it doesn't support it.
+    /// - `helper:<command> [args...]`: runs `<command>` with the whitespace-separated
+    ///   `args` as a credential helper to get an API key from, which is never stored in
+    ///   the `auth.yaml` file.
     value: Option<Repr>,
 
     #[clap(flatten)]
@@ -46,10 +49,10 @@ pub fn run(
         context,
         registry,
         value,
-        args: auth::Args { file },
+        args,
     }: Args,
 ) -> eyre::Result<()> {
-    let auth = Auth::load(file)?;
+    let auth = Auth::load_for_update(&args)?;
     if auth.credentials.contains_key(&context) {
         error!("context {} is already avaliable", context);
         info!(
@@ -64,6 +67,6 @@ pub fn run(
     trace!("authentication credentials: {:?}", value);
 
     auth.commit(|me| {
-        me.credentials.insert(context, Credential { registry, repr: value });
+        me.credentials.insert(context, Credential::new(registry, value));
     })
 }
@@ -0,0 +1,36 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::auth::{self, Auth};
+
+/// Decrypt the `auth.yaml` file and store it as plaintext again.
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    auth: auth::Args,
+}
+
+pub fn run(Args { auth }: Args) -> eyre::Result<()> {
+    let mut auth = Auth::load_for_update(&auth)?;
+    if auth.encryption().is_none() {
+        warn!("`auth.yaml` file is not encrypted");
+        return Ok(());
+    }
+
+    info!("decrypting `auth.yaml` file");
+
+    auth.set_encryption(None);
+    auth.save()
+}
@@ -12,3 +12,61 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use crate::auth::{self, Auth, Context};
+use std::process::exit;
+
+/// Deletes a context.
+///
+/// This only removes the context from the `auth.yaml` file, use `helm charted logout`
+/// to also revoke its API key on the registry.
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// Context to delete.
+    context: Context,
+
+    /// allows deleting the current context, another context will become the
+    /// current one.
+    #[arg(short = 'f', long, default_value_t = false)]
+    force: bool,
+
+    #[clap(flatten)]
+    auth: auth::Args,
+}
+
+pub fn run(Args { context, force, auth }: Args) -> eyre::Result<()> {
+    let mut auth = Auth::load_for_update(&auth)?;
+    if !auth.credentials.contains_key(&context) {
+        error!("context '{}' doesn't exist!", context);
+        exit(1);
+    }
+
+    let was_current = auth.current == context;
+    if was_current && !force {
+        error!(
+            "context '{}' is the current context, switch to another context first or pass `--force`",
+            context
+        );
+
+        exit(1);
+    }
+
+    let shadowed = auth.shadowed(&context);
+    auth.remove(&context);
+    info!("deleted context '{}'", context);
+
+    if !shadowed.is_empty() {
+        warn!(
+            "context '{}' is still defined in the {} layer(s), use `helm charted context explain {}` to see where",
+            context,
+            shadowed.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "),
+            context
+        );
+    }
+
+    if was_current {
+        info!("current context is now '{}'", auth.current);
+    }
+
+    auth.save()
+}
@@ -0,0 +1,69 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::auth::{
+    self, Auth,
+    encryption::{Encryption, PASSPHRASE_ENV},
+};
+use secrecy::{ExposeSecret, SecretString};
+use std::{env, path::PathBuf};
+
+/// Encrypt the `auth.yaml` file at rest.
+///
+/// The file is encrypted with the passphrase from `$CHARTED_HELM_AUTH_PASSPHRASE`,
+/// or one that is prompted for, unless `--identity` is given. Afterwards, the
+/// same passphrase or `$CHARTED_HELM_AUTH_IDENTITY` is needed to unlock it.
+///
+/// Running this on an already encrypted file will re-encrypt it.
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// Encrypts to the X25519 identity in an age identity file instead of a passphrase.
+    #[arg(long, value_name = "PATH")]
+    identity: Option<PathBuf>,
+
+    #[clap(flatten)]
+    auth: auth::Args,
+}
+
+pub fn run(Args { identity, auth }: Args) -> eyre::Result<()> {
+    let mut auth = Auth::load_for_update(&auth)?;
+    let encryption = match identity {
+        Some(path) => Encryption::from_identity_file(path)?,
+        None => Encryption::Passphrase(passphrase()?),
+    };
+
+    info!("encrypting `auth.yaml` file");
+
+    auth.set_encryption(Some(encryption));
+    auth.save()
+}
+
+fn passphrase() -> eyre::Result<SecretString> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        return Ok(SecretString::new(passphrase.into()));
+    }
+
+    let passphrase = SecretString::new(rpassword::prompt_password("New passphrase: ")?.into());
+    if passphrase.expose_secret().is_empty() {
+        bail!("passphrase can't be empty");
+    }
+
+    let confirmation = SecretString::new(rpassword::prompt_password("Confirm passphrase: ")?.into());
+    if passphrase.expose_secret() != confirmation.expose_secret() {
+        bail!("passphrases didn't match");
+    }
+
+    Ok(passphrase)
+}
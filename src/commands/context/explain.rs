@@ -0,0 +1,82 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::auth::{self, Auth, Context};
+use std::{path::Path, process::exit};
+
+/// Explains which `auth.yaml` layer each context was loaded from.
+///
+/// Contexts in a higher layer (system < user < project) shadow the contexts with
+/// the same name in lower layers.
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// Only explain this context.
+    context: Option<Context>,
+
+    #[clap(flatten)]
+    auth: auth::Args,
+}
+
+pub fn run(Args { context, auth }: Args) -> eyre::Result<()> {
+    let auth = Auth::load(&auth)?;
+
+    let mut contexts = match context {
+        Some(context) if !auth.credentials.contains_key(&context) => {
+            error!("context '{}' doesn't exist!", context);
+            exit(1);
+        }
+
+        Some(context) => vec![context],
+        None => {
+            println!("Layers (from lowest to highest precedence):");
+            for (layer, path) in auth.layers() {
+                println!("~> {:11}  {}", layer.to_string(), describe(path));
+            }
+
+            println!();
+            auth.credentials.keys().cloned().collect()
+        }
+    };
+
+    contexts.sort();
+    for context in contexts {
+        let marker = if auth.current == context { " (current)" } else { "" };
+        let Some((layer, path)) = auth.origin(&context) else {
+            println!("{context}{marker}: not saved yet");
+            continue;
+        };
+
+        println!("{context}{marker}: {layer} layer ({})", describe(path));
+
+        let shadowed = auth.shadowed(&context);
+        if !shadowed.is_empty() {
+            println!(
+                "    shadows the same context in the {} layer(s)",
+                shadowed.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn describe(path: &Path) -> String {
+    // the environment layer doesn't have a file.
+    if path.as_os_str().is_empty() {
+        String::from("environment variables")
+    } else {
+        path.display().to_string()
+    }
+}
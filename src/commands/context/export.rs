@@ -13,50 +13,213 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::auth::{self, Auth, Context};
-use serde_json::json;
-use std::process::exit;
+use crate::{
+    auth::{self, Auth, Context, Credential, Repr},
+    http,
+};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Write, process::exit};
 
-/// Exports a context as JSON.
+/// Exports a context.
+///
+/// Secrets are redacted unless `--show-secrets` is passed, redacted contexts
+/// can't be imported with `helm charted context import`.
 #[derive(Debug, clap::Parser)]
 pub struct Args {
+    /// Context to export. Defaults to the current context.
     context: Option<Context>,
 
+    /// Format to export the context as.
+    #[arg(short = 'o', long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
+    /// includes API keys and passwords in the output
+    #[arg(long, default_value_t = false)]
+    show_secrets: bool,
+
+    /// User or organization to point the Helm repository to, required for
+    /// `--format=helm`.
+    #[arg(long, required_if_eq("format", "helm"))]
+    owner: Option<String>,
+
     #[clap(flatten)]
     auth: auth::Args,
 }
 
-pub fn run(Args { context, auth }: Args) -> eyre::Result<()> {
-    let auth = Auth::load(auth.file)?;
-    let Some(context) = context else {
-        // paranoia check
-        assert!(auth.credentials.contains_key(&auth.current));
+/// Formats that a context can be exported as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Format {
+    /// JSON that can be imported with `helm charted context import`.
+    Json,
 
-        let credential = unsafe { auth.credentials.get(&auth.current).unwrap_unchecked() };
-        println!(
-            "{}",
-            serde_json::to_string(&json!({
-                "context": auth.current,
-                "credential": credential
-            }))?
-        );
+    /// YAML with the same schema as the JSON format, which can also be imported.
+    Yaml,
 
-        return Ok(());
-    };
+    /// `CHARTED_HELM_*` environment variables that can be used with a `.env` file.
+    Dotenv,
+
+    /// An entry for Helm's `repositories.yaml` that uses the `charted://` protocol.
+    Helm,
+}
 
-    if !auth.credentials.contains_key(&context) {
+/// A context that was exported, which can be imported again with
+/// `helm charted context import`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Exported {
+    pub context: Context,
+    pub credential: Credential,
+}
+
+/// An entry in Helm's `repositories.yaml` file.
+#[derive(Debug, Serialize)]
+struct HelmRepository {
+    name: String,
+    url: String,
+}
+
+pub fn run(
+    Args {
+        context,
+        format,
+        show_secrets,
+        owner,
+        auth,
+    }: Args,
+) -> eyre::Result<()> {
+    let mut auth = Auth::load(&auth)?;
+    let context = context.unwrap_or_else(|| auth.current.clone());
+
+    let Some(mut credential) = auth.credentials.remove(&context) else {
         warn!("context '{}' doesn't exist!", context);
         exit(1);
+    };
+
+    if !show_secrets {
+        credential.repr = credential.repr.as_ref().map(Repr::redacted);
+        credential.http.proxy = credential.http.proxy.as_ref().map(http::Proxy::redacted);
     }
 
-    let credential = unsafe { auth.credentials.get(&context).unwrap_unchecked() };
-    println!(
-        "{}",
-        serde_json::to_string(&json!({
-            "context": context,
-            "credential": credential
-        }))?
-    );
+    let exported = Exported { context, credential };
+    match format {
+        Format::Json => println!("{}", serde_json::to_string(&exported)?),
+        Format::Yaml => print!("{}", serde_yaml_ng::to_string(&exported)?),
+        Format::Dotenv => print!("{}", dotenv(&exported)?),
+        Format::Helm => {
+            let owner = owner.expect("`--owner` to be required by clap");
+            print!("{}", serde_yaml_ng::to_string(&[helm_repository(&exported, owner)?])?);
+        }
+    }
 
     Ok(())
 }
+
+/// Renders the context as `CHARTED_HELM_*` environment variables.
+fn dotenv(Exported { context, credential }: &Exported) -> eyre::Result<String> {
+    let mut buf = String::new();
+    writeln!(buf, "CHARTED_HELM_CONTEXT={}", quote(&context.to_string()))?;
+    writeln!(buf, "CHARTED_HELM_REGISTRY={}", quote(credential.registry.as_str()))?;
+
+    match credential.repr {
+        Some(Repr::ApiKey(ref key)) => writeln!(buf, "CHARTED_HELM_TOKEN={}", quote(key.expose_secret()))?,
+        Some(Repr::EnvironmentVariable(ref key)) => writeln!(buf, "CHARTED_HELM_TOKEN=\"${{{key}}}\"")?,
+        Some(Repr::Basic {
+            ref username,
+            ref password,
+        }) => {
+            writeln!(buf, "CHARTED_HELM_USERNAME={}", quote(username))?;
+            writeln!(buf, "CHARTED_HELM_PASSWORD={}", quote(password.expose_secret()))?;
+        }
+
+        Some(Repr::Helper { .. }) => {
+            writeln!(buf, "# context '{context}' gets its token from a credential helper")?;
+        }
+
+        None => {}
+    }
+
+    Ok(buf)
+}
+
+/// Returns a Helm repository that points to `owner`'s charts in the context's
+/// registry, which is served by the `charted://` downloader.
+///
+/// The downloader always maps `charted://{host}` onto `https://{host}/api/v1`, so
+/// registries that live anywhere else can't be exported.
+fn helm_repository(Exported { credential, .. }: &Exported, owner: String) -> eyre::Result<HelmRepository> {
+    let Some(host) = credential.registry.host_str() else {
+        bail!("registry {} doesn't contain a host", credential.registry);
+    };
+
+    if credential.registry.scheme() != "https" || credential.registry.path().trim_end_matches('/') != "/api/v1" {
+        bail!(
+            "registry {} can't be used by the `charted://` downloader, which only supports `https://{{host}}/api/v1`",
+            credential.registry
+        );
+    }
+
+    let url = match credential.registry.port() {
+        Some(port) => format!("charted://{host}:{port}/{owner}"),
+        None => format!("charted://{host}/{owner}"),
+    };
+
+    Ok(HelmRepository { name: owner, url })
+}
+
+/// Quotes `value` so that it can be used in a `.env` file.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        if matches!(ch, '\\' | '"' | '$') {
+            quoted.push('\\');
+        }
+
+        quoted.push(ch);
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Exported, dotenv, helm_repository};
+    use crate::auth::{Credential, Repr};
+    use url::Url;
+
+    fn exported(repr: Repr) -> Exported {
+        Exported {
+            context: "default".into(),
+            credential: Credential::new(Url::parse("http://localhost:3651/api/v1").unwrap(), Some(repr)),
+        }
+    }
+
+    #[test]
+    fn dotenv_format() {
+        assert_eq!(
+            "CHARTED_HELM_CONTEXT=\"default\"\nCHARTED_HELM_REGISTRY=\"http://localhost:3651/api/v1\"\nCHARTED_HELM_TOKEN=\"a\\\"b\\$c\"\n",
+            dotenv(&exported("apikey:a\"b$c".parse().unwrap())).unwrap()
+        );
+
+        assert!(
+            dotenv(&exported("env:token".parse().unwrap()))
+                .unwrap()
+                .ends_with("CHARTED_HELM_TOKEN=\"${TOKEN}\"\n")
+        );
+    }
+
+    #[test]
+    fn helm_format() {
+        let mut exported = exported("env:token".parse().unwrap());
+        assert!(helm_repository(&exported, String::from("noel")).is_err());
+
+        exported.credential.registry = Url::parse("https://localhost:3651/api/v1").unwrap();
+        let repository = helm_repository(&exported, String::from("noel")).unwrap();
+        assert_eq!("charted://localhost:3651/noel", repository.url);
+
+        exported.credential.registry = Url::parse("https://localhost:3651/charted/api/v1").unwrap();
+        assert!(helm_repository(&exported, String::from("noel")).is_err());
+    }
+}
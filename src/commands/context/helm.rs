@@ -0,0 +1,359 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    Globals,
+    auth::{self, Auth, Context, Credential, Repr},
+    commands::login,
+    config::Config,
+    http,
+};
+use etcetera::{BaseStrategy, base_strategy::choose_native_strategy};
+use eyre::Context as _;
+use secrecy::SecretString;
+use serde::Deserialize;
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
+use url::Url;
+
+/// Imports contexts from the Helm repositories that point to **charted-server**.
+///
+/// Repositories that use the `charted://` scheme, the official hub, or a
+/// registry's `/indexes/{owner}` endpoint are imported as a context with the
+/// same name as the repository. Usernames and passwords are imported as Basic
+/// credentials, which can be upgraded to an API key instead.
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// Location to Helm's `repositories.yaml` file.
+    #[arg(long, env = "HELM_REPOSITORY_CONFIG")]
+    repository_config: Option<PathBuf>,
+
+    /// replaces contexts that already exist
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
+
+    /// upgrades Basic credentials to an API key without asking
+    #[arg(long, default_value_t = false, conflicts_with = "no_upgrade")]
+    upgrade: bool,
+
+    /// keeps Basic credentials as-is without asking
+    #[arg(long, default_value_t = false)]
+    no_upgrade: bool,
+
+    #[clap(flatten)]
+    http: http::Args,
+
+    #[clap(flatten)]
+    auth: auth::Args,
+}
+
+/// The subset of Helm's `repositories.yaml` file that is used.
+#[derive(Debug, Deserialize)]
+struct RepositoryFile {
+    #[serde(default)]
+    repositories: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    name: String,
+    url: Url,
+
+    #[serde(default)]
+    username: String,
+
+    #[serde(default)]
+    password: String,
+
+    #[serde(default)]
+    cert_file: String,
+
+    #[serde(default)]
+    key_file: String,
+
+    #[serde(default)]
+    ca_file: String,
+
+    #[serde(default, rename = "insecure_skip_tls_verify")]
+    insecure: bool,
+}
+
+impl Entry {
+    /// Returns the [`Credential`] for this repository, or `None` if it doesn't
+    /// point to **charted-server**.
+    fn credential(&self) -> eyre::Result<Option<Credential>> {
+        let Some(registry) = registry_of(&self.url)? else {
+            return Ok(None);
+        };
+
+        let repr = (!self.username.is_empty()).then(|| Repr::Basic {
+            username: self.username.clone(),
+            password: SecretString::from(self.password.as_str()),
+        });
+
+        let mut credential = Credential::new(registry, repr);
+        if !self.ca_file.is_empty() {
+            credential.http.certificates.push(
+                self.ca_file
+                    .parse()
+                    .with_context(|| format!("invalid ca file for repository '{}'", self.name))?,
+            );
+        }
+
+        if !self.cert_file.is_empty() {
+            credential.http.identity = Some(http::Identity {
+                cert: PathBuf::from(&self.cert_file),
+                key: (!self.key_file.is_empty()).then(|| PathBuf::from(&self.key_file)),
+            });
+        }
+
+        credential.http.insecure = self.insecure;
+        Ok(Some(credential))
+    }
+}
+
+/// Returns the registry (including the API version) that a Helm repository's
+/// URL points to, or `None` if it isn't backed by **charted-server**.
+fn registry_of(url: &Url) -> eyre::Result<Option<Url>> {
+    let Some(host) = url.host_str() else {
+        return Ok(None);
+    };
+
+    let authority = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_owned(),
+    };
+
+    // `charted://` urls are served by the downloader, which always uses https.
+    if url.scheme() == "charted" || host == login::OFFICIAL_HUB {
+        return Ok(Some(Url::parse(&format!("https://{authority}/api/v1"))?));
+    }
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Ok(None);
+    }
+
+    let segments = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    match segments.iter().position(|segment| *segment == "indexes") {
+        Some(index) if index > 0 => Ok(Some(Url::parse(&format!(
+            "{}://{authority}/{}",
+            url.scheme(),
+            segments[..index].join("/")
+        ))?)),
+
+        _ => Ok(None),
+    }
+}
+
+/// Returns the location of Helm's `repositories.yaml` file if `$HELM_REPOSITORY_CONFIG`
+/// isn't set, which mirrors what `helm env` reports.
+fn default_repository_config() -> eyre::Result<PathBuf> {
+    if let Some(home) = env::var_os("HELM_CONFIG_HOME") {
+        return Ok(PathBuf::from(home).join("repositories.yaml"));
+    }
+
+    let strategy = choose_native_strategy()?;
+
+    #[cfg(target_os = "macos")]
+    let dir = strategy.home_dir().join("Library/Preferences/helm");
+
+    #[cfg(not(target_os = "macos"))]
+    let dir = strategy.config_dir().join("helm");
+
+    Ok(dir.join("repositories.yaml"))
+}
+
+pub async fn run(
+    Args {
+        repository_config,
+        overwrite,
+        upgrade,
+        no_upgrade,
+        http,
+        auth: auth_args,
+    }: Args,
+    globals: Globals,
+) -> eyre::Result<()> {
+    if upgrade {
+        globals.ensure_online("upgrading Basic credentials to an API key")?;
+    }
+
+    let path = match repository_config {
+        Some(path) => path,
+        None => default_repository_config()?,
+    };
+
+    debug!(path = %path.display(), "reading helm repositories");
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read helm repositories from {}", path.display()))?;
+
+    let file = serde_yaml_ng::from_str::<RepositoryFile>(&contents)
+        .with_context(|| format!("failed to deserialize helm repositories from {}", path.display()))?;
+
+    // the files are only locked once every repository was imported, so other
+    // commands aren't blocked while prompting or creating API keys.
+    let auth = Auth::load(&auth_args)?;
+    let config = http.config();
+    // nothing is upgraded in offline mode, so there's nothing to ask.
+    let interactive = io::stdin().is_terminal() && !globals.offline;
+    let mut imported = Vec::new();
+
+    for entry in &file.repositories {
+        let Some(mut credential) = entry.credential()? else {
+            trace!(repository = entry.name, url = %entry.url, "skipping repository that isn't backed by charted-server");
+            continue;
+        };
+
+        let context = Context::new(&entry.name);
+        if auth.credentials.contains_key(&context) && !overwrite {
+            warn!(
+                "context '{}' already exists, skipping repository (use `--overwrite` to replace it)",
+                context
+            );
+
+            continue;
+        }
+
+        if let Some(Repr::Basic {
+            ref username,
+            ref password,
+        }) = credential.repr
+        {
+            let upgrade = !no_upgrade &&
+                (upgrade ||
+                    interactive &&
+                        confirm(&format!(
+                            "Create an API key for context '{context}' instead of storing its password? [Y/n] "
+                        ))?);
+
+            if upgrade {
+                match upgrade_to_api_key(&http, config.as_ref(), &credential, username, password).await {
+                    Ok(upgraded) => credential = upgraded,
+                    Err(e) => warn!(
+                        "failed to create an api key for context '{}', keeping its Basic credentials: {:?}",
+                        context, e
+                    ),
+                }
+            }
+        }
+
+        info!(
+            "importing repository '{}' as context '{}' that points to registry {}",
+            entry.name, context, credential.registry
+        );
+
+        imported.push((context, credential));
+    }
+
+    if imported.is_empty() {
+        warn!("no repositories in {} were imported", path.display());
+        return Ok(());
+    }
+
+    Auth::load_for_update(&auth_args)?.commit(|me| {
+        for (context, credential) in imported {
+            // the context could've been created by another command in the meantime.
+            if me.credentials.contains_key(&context) && !overwrite {
+                warn!("context '{}' was created in the meantime, skipping it", context);
+                continue;
+            }
+
+            me.credentials.insert(context, credential);
+        }
+    })
+}
+
+/// Creates an API key from Basic credentials and returns the credential that
+/// uses it, with the same HTTP settings.
+async fn upgrade_to_api_key(
+    http: &http::Args,
+    config: Option<&Config>,
+    credential: &Credential,
+    username: &str,
+    password: &SecretString,
+) -> eyre::Result<Credential> {
+    // the session is created with the Basic credentials explicitly.
+    let client = http.client(
+        config,
+        &credential.registry,
+        Some(&Credential {
+            repr: None,
+            ..credential.clone()
+        }),
+    )?;
+
+    let access_token = login::session(&client, &credential.registry, username, password).await?;
+    let upgraded = login::create_api_key(
+        &client,
+        credential.registry.clone(),
+        &access_token,
+        None,
+        &[],
+        "API key created by `helm charted context import-helm`",
+    )
+    .await?;
+
+    Ok(Credential {
+        http: credential.http.clone(),
+        ..upgraded
+    })
+}
+
+/// Asks a yes or no question, defaulting to yes.
+fn confirm(message: &str) -> io::Result<bool> {
+    let answer = login::prompt(message)?;
+    Ok(answer.is_empty() || answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::registry_of;
+    use url::Url;
+
+    #[test]
+    fn registries() {
+        for (url, expected) in [
+            (
+                "charted://charts.noelware.org/noel",
+                Some("https://charts.noelware.org/api/v1"),
+            ),
+            ("charted://localhost:3651/noel", Some("https://localhost:3651/api/v1")),
+            (
+                "https://charts.noelware.org/api/v1/indexes/noel",
+                Some("https://charts.noelware.org/api/v1"),
+            ),
+            (
+                "http://localhost:3651/v1/indexes/noel",
+                Some("http://localhost:3651/v1"),
+            ),
+            ("https://charts.bitnami.com/bitnami", None),
+        ] {
+            assert_eq!(
+                expected.map(|url| Url::parse(url).unwrap()),
+                registry_of(&Url::parse(url).unwrap()).unwrap(),
+                "{url}"
+            );
+        }
+    }
+}
@@ -0,0 +1,242 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::export::Exported;
+use crate::{
+    auth::{self, Auth, Context, Repr},
+    http,
+};
+use eyre::Context as _;
+use std::{collections::HashSet, fs, io, path::PathBuf, process::exit};
+
+/// Imports contexts that were exported with `helm charted context export`.
+///
+/// This accepts either a single exported context or an array of them, in the
+/// JSON or YAML format. Nothing is imported if any of the contexts are invalid or already
+/// exist, unless `--overwrite` or `--rename` is passed.
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// File to read the exported contexts from, or `-` to read from stdin.
+    #[arg(value_name = "FILE", default_value = "-")]
+    input: PathBuf,
+
+    /// replaces contexts that already exist
+    #[arg(long, default_value_t = false, conflicts_with = "rename")]
+    overwrite: bool,
+
+    /// imports contexts that already exist under a new name (i.e, `default-1`)
+    #[arg(long, default_value_t = false)]
+    rename: bool,
+
+    /// allows importing contexts that use a credential helper, an API key from
+    /// an environment variable, or the `insecure` and `proxy` settings. Only pass
+    /// this for input that you trust.
+    #[arg(long, default_value_t = false)]
+    trust: bool,
+
+    #[clap(flatten)]
+    auth: auth::Args,
+}
+
+pub fn run(
+    Args {
+        input,
+        overwrite,
+        rename,
+        trust,
+        auth,
+    }: Args,
+) -> eyre::Result<()> {
+    let contents = if input.as_os_str() == "-" {
+        io::read_to_string(io::stdin()).context("failed to read exported contexts from stdin")?
+    } else {
+        fs::read_to_string(&input).with_context(|| format!("failed to read file: {}", input.display()))?
+    };
+
+    let entries = parse(&contents, trust)?;
+    let auth = Auth::load_for_update(&auth)?;
+
+    let existing = entries
+        .iter()
+        .filter(|entry| auth.credentials.contains_key(&entry.context))
+        .map(|entry| entry.context.to_string())
+        .collect::<Vec<_>>();
+
+    if !existing.is_empty() && !overwrite && !rename {
+        error!(
+            "context(s) {} already exist, use `--overwrite` to replace them or `--rename` to import them under a new name",
+            existing.join(", ")
+        );
+
+        exit(1);
+    }
+
+    auth.commit(|me| {
+        for Exported { context, credential } in entries {
+            let context = if rename && me.credentials.contains_key(&context) {
+                let renamed = (1..)
+                    .map(|i| Context::new(format!("{context}-{i}")))
+                    .find(|candidate| !me.credentials.contains_key(candidate))
+                    .expect("to find an unused context name");
+
+                info!("context '{}' already exists, importing it as '{}'", context, renamed);
+                renamed
+            } else {
+                context
+            };
+
+            info!(
+                "importing context '{}' that points to registry {} with {}",
+                context,
+                credential.registry,
+                credential
+                    .repr
+                    .as_ref()
+                    .map_or_else(|| String::from("no credentials"), ToString::to_string)
+            );
+
+            let untrusted = credential.untrusted_settings();
+            if !untrusted.is_empty() {
+                warn!("context '{}' uses {}", context, untrusted.join(", "));
+            }
+
+            me.credentials.insert(context, credential);
+        }
+    })
+}
+
+/// Parses and validates a single exported context or an array of them. Since
+/// JSON is valid YAML, both formats are parsed as YAML.
+///
+/// Unless `trust` is `true`, contexts that use any of the [untrusted
+/// settings](crate::auth::Credential::untrusted_settings) are rejected.
+fn parse(contents: &str, trust: bool) -> eyre::Result<Vec<Exported>> {
+    let value =
+        serde_yaml_ng::from_str::<serde_yaml_ng::Value>(contents).context("failed to parse exported contexts")?;
+
+    let entries = if value.is_sequence() {
+        serde_yaml_ng::from_value::<Vec<Exported>>(value)
+    } else {
+        serde_yaml_ng::from_value::<Exported>(value).map(|entry| vec![entry])
+    }
+    .context("failed to parse exported contexts")?;
+
+    let mut seen = HashSet::new();
+    for Exported { context, credential } in &entries {
+        if context.to_string().trim().is_empty() {
+            bail!("context name can't be empty");
+        }
+
+        if !seen.insert(context) {
+            bail!("context '{context}' was given more than once");
+        }
+
+        if !matches!(credential.registry.scheme(), "http" | "https") {
+            bail!(
+                "context '{context}' points to registry {}, which isn't a http(s) url",
+                credential.registry
+            );
+        }
+
+        if credential.repr.as_ref().is_some_and(Repr::is_redacted) ||
+            credential.http.proxy.as_ref().is_some_and(http::Proxy::is_redacted)
+        {
+            bail!("context '{context}' was exported with its secrets redacted, export it again with `--show-secrets`");
+        }
+
+        let untrusted = credential.untrusted_settings();
+        if !untrusted.is_empty() && !trust {
+            bail!(
+                "context '{context}' uses {}, pass `--trust` to import it anyway",
+                untrusted.join(", ")
+            );
+        }
+
+        if credential
+            .remaining()
+            .is_some_and(|remaining| remaining <= chrono::TimeDelta::zero())
+        {
+            bail!("the api key for context '{context}' has already expired");
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Exported, parse};
+    use crate::auth::{Context, Credential, Repr};
+    use url::Url;
+
+    #[test]
+    fn parse_single_and_array() {
+        let single = r#"{"context":"default","credential":{"registry":"https://charts.noelware.org/api/v1"}}"#;
+        assert_eq!(1, parse(single, false).unwrap().len());
+
+        let array = format!(
+            r#"[{single}, {{"context":"local","credential":{{"registry":"http://localhost:3651","repr":{{"api-key":"abc"}}}}}}]"#
+        );
+
+        assert_eq!(2, parse(&array, false).unwrap().len());
+        assert!(parse(&format!("[{single}, {single}]"), false).is_err());
+        assert!(
+            parse(
+                r#"{"context":"default","credential":{"registry":"ftp://localhost"}}"#,
+                false
+            )
+            .is_err()
+        );
+        assert!(
+            parse(
+                r#"{"context":"default","credential":{"registry":"http://localhost","repr":{"api-key":"<redacted>"}}}"#,
+                false
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_yaml() {
+        let exported = Exported {
+            context: "local".into(),
+            credential: Credential::new(
+                Url::parse("http://localhost:3651/api/v1").unwrap(),
+                Some("apikey:abc".parse().unwrap()),
+            ),
+        };
+
+        let entries = parse(&serde_yaml_ng::to_string(&exported).unwrap(), false).unwrap();
+        assert_eq!(Context::new("local"), entries[0].context);
+        assert!(matches!(entries[0].credential.repr, Some(Repr::ApiKey(_))));
+
+        let array = "- context: default\n  credential:\n    registry: https://charts.noelware.org/api/v1\n- context: local\n  credential:\n    registry: http://localhost:3651\n";
+        assert_eq!(2, parse(array, false).unwrap().len());
+    }
+
+    #[test]
+    fn untrusted_settings_need_trust() {
+        for credential in [
+            r#"{"registry":"http://localhost","repr":{"helper":{"command":"sh"}}}"#,
+            r#"{"registry":"http://localhost","repr":{"environment-variable":"AWS_SECRET_ACCESS_KEY"}}"#,
+            r#"{"registry":"http://localhost","http":{"insecure":true}}"#,
+            r#"{"registry":"http://localhost","http":{"proxy":"http://localhost:3128"}}"#,
+        ] {
+            let input = format!(r#"{{"context":"default","credential":{credential}}}"#);
+            assert!(parse(&input, false).is_err(), "{credential}");
+            assert!(parse(&input, true).is_ok(), "{credential}");
+        }
+    }
+}
@@ -12,3 +12,102 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use crate::auth::{self, Auth, Context};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use url::Url;
+
+/// Lists all the avaliable authentication contexts.
+///
+/// The current context is marked with a `*`, or with a `@` if it was pinned for
+/// the working directory. Secrets are never shown, only the kind of
+/// authentication that a context uses.
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// reports as JSON instead of a table
+    #[arg(short = 'j', long, default_value_t = false)]
+    json: bool,
+
+    #[clap(flatten)]
+    auth: auth::Args,
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    name: &'a Context,
+    registry: &'a Url,
+    auth: &'static str,
+    current: bool,
+    pinned: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+pub fn run(Args { json, auth }: Args) -> eyre::Result<()> {
+    let auth = Auth::load(&auth)?;
+
+    let mut entries = auth
+        .credentials
+        .iter()
+        .map(|(context, credential)| Entry {
+            name: context,
+            registry: &credential.registry,
+            auth: credential.repr.as_ref().map(|repr| repr.kind()).unwrap_or("none"),
+            current: auth.current == *context,
+            pinned: auth.pin().is_some_and(|pin| pin.context == *context),
+            expires_at: credential.expires_at,
+        })
+        .collect::<Vec<_>>();
+
+    entries.sort_by(|a, b| a.name.cmp(b.name));
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    let rows = entries
+        .iter()
+        .map(|entry| {
+            [
+                String::from(match (entry.current, entry.pinned) {
+                    (_, true) => "@",
+                    (true, false) => "*",
+                    (false, false) => "",
+                }),
+                entry.name.to_string(),
+                entry.registry.to_string(),
+                entry.auth.to_owned(),
+                auth.credentials[entry.name].describe_expiry(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let header = ["", "NAME", "REGISTRY", "AUTH", "EXPIRES"].map(String::from);
+    let mut widths = [0; 5];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.len());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(column, width)| format!("{column:width$}"))
+            .collect::<Vec<_>>()
+            .join("   ");
+
+        println!("{}", line.trim_end());
+    }
+
+    if let Some(pin) = auth.pin() {
+        println!();
+        println!("~> context '{}' is pinned by {}", pin.context, pin.path.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::auth::{self, Auth};
+
+/// Removes all contexts whose API key has expired.
+///
+/// Expired contexts are also removed whenever a command changes the `auth.yaml`
+/// file, i.e: `helm charted login`.
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    auth: auth::Args,
+}
+
+pub fn run(Args { auth }: Args) -> eyre::Result<()> {
+    let mut auth = Auth::load_for_update(&auth)?;
+    if auth.prune().is_empty() {
+        info!("no contexts have expired");
+        return Ok(());
+    }
+
+    auth.save()
+}
@@ -0,0 +1,56 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::auth::{self, Auth, Context};
+use std::process::exit;
+
+/// Shows the details of a context. Secrets are always redacted.
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// Context to show. Defaults to the current context.
+    context: Option<Context>,
+
+    #[clap(flatten)]
+    auth: auth::Args,
+}
+
+pub fn run(Args { context, auth }: Args) -> eyre::Result<()> {
+    let auth = Auth::load(&auth)?;
+    let context = context.unwrap_or_else(|| auth.current.clone());
+
+    let Some(credential) = auth.credentials.get(&context) else {
+        error!("context '{}' doesn't exist!", context);
+        exit(1);
+    };
+
+    let repr = match credential.repr {
+        Some(ref repr) => repr.to_string(),
+        None => String::from("none"),
+    };
+
+    println!(
+        "~> Context:  {context}{}",
+        if auth.current == context { " (current)" } else { "" }
+    );
+    println!("~> Registry: {}", credential.registry);
+    println!("~> Auth:     {repr}");
+
+    if let Some(ref name) = credential.api_key {
+        println!("~> API Key:  {name}");
+    }
+
+    println!("~> Expires:  {}", credential.describe_expiry());
+    Ok(())
+}
@@ -13,26 +13,68 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::auth::{self, Auth, Context};
-use std::process::exit;
+use crate::auth::{self, Auth, Context, pin::Pin};
+use std::{env, path::Path, process::exit};
 
 /// Switch the default context.
+///
+/// If a context is pinned for the working directory by a `.charted-context`
+/// file, then that file is updated instead so other projects aren't affected.
 #[derive(Debug, clap::Parser)]
 pub struct Args {
     /// Context to switch to.
     context: Context,
 
+    /// pins the context for the working directory with a `.charted-context` file
+    /// instead of switching the default context.
+    #[arg(long, default_value_t = false)]
+    pin: bool,
+
     #[clap(flatten)]
     auth: auth::Args,
 }
 
-pub fn run(Args { context, auth }: Args) -> eyre::Result<()> {
-    let auth = Auth::load(auth.file)?;
+pub fn run(Args { context, pin, auth }: Args) -> eyre::Result<()> {
+    let auth = Auth::load_for_update(&auth)?;
     if !auth.credentials.contains_key(&context) {
         error!("context '{}' doesn't exist!", context);
         exit(1);
     }
 
+    if pin {
+        let pin = Pin::write(&env::current_dir()?, &context)?;
+        info!("pinned context '{}' in {}", context, pin.path.display());
+
+        return Ok(());
+    }
+
+    if let Some(pin) = auth.pin() {
+        if !pin.is_pin_file() {
+            error!(
+                "context '{}' is pinned by the `global.context` setting in {}, change it there or use `--pin` to override it",
+                pin.context,
+                pin.path.display()
+            );
+
+            exit(1);
+        }
+
+        if pin.context == context {
+            warn!("pinned context is already pointing to {}", context);
+            return Ok(());
+        }
+
+        info!(
+            "switching pinned context from {} ~> {} in {}",
+            pin.context,
+            context,
+            pin.path.display()
+        );
+
+        Pin::write(pin.path.parent().unwrap_or(Path::new(".")), &context)?;
+        return Ok(());
+    }
+
     if auth.current == context {
         warn!("default context is already pointing to {}", context);
         return Ok(());
@@ -42,6 +84,6 @@ pub fn run(Args { context, auth }: Args) -> eyre::Result<()> {
     info!("switching from {} ~> {}", current, context);
 
     auth.commit(|me| {
-        me.current = context;
+        me.set_default(context);
     })
 }
@@ -13,18 +13,173 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::PathBuf;
+use crate::{
+    Globals,
+    auth::{self, Auth, Credential},
+    cache::Cache,
+    config::{Config, Path, registry::Registry},
+    http,
+};
+use charted_core::api;
+use charted_types::name::Name;
+use eyre::Context;
+use std::{
+    ffi::OsString,
+    io::{self, Write},
+    path::PathBuf,
+};
 use url::Url;
 
 /// Implements Helm's [Downloader Protocol] feature for plugins.
 ///
+/// Helm will invoke this subcommand with a `charted://` URL, which is mapped
+/// onto the registry's endpoints and the response is written into stdout:
+///
+/// | URL                                             | Endpoint                                                       |
+/// | :---------------------------------------------- | :------------------------------------------------------------- |
+/// | `charted://{host}/{owner}/index.yaml`           | `GET https://{host}/api/v1/indexes/{owner}`                    |
+/// | `charted://{host}/{owner}/{repo}/{version}.tgz` | `GET https://{host}/api/v1/repositories/{owner}/{repo}/releases/{version}/tarball` |
+///
+/// Since Helm doesn't pass in a context, the credential is picked from the
+/// `auth.yaml` file by the registry that is the longest prefix of the endpoint,
+/// or the current context if none match and it's on the same host. Otherwise,
+/// the request is sent without any credentials.
+///
+/// Responses are kept in the [`Cache`], so an `index.yaml` is only downloaded
+/// again if it changed and a chart tarball is never downloaded twice. With
+/// `--offline`, resources are only served from the cache.
+///
+/// The certificate, key and CA files are the ones given to `helm repo add`, Helm
+/// will pass in empty arguments if they weren't provided. They're used on top of
+/// the HTTP settings of the context, since Helm can't pass in any other flags.
+///
 /// [Downloader Protocol]: https://helm.sh/docs/topics/plugins/#downloader-plugins
 #[derive(Debug, clap::Parser)]
 pub struct Args {
-    cert_file: PathBuf,
-    key_file: PathBuf,
-    ca_file: PathBuf,
+    // these are `OsString`s since `PathBuf`'s value parser rejects empty values.
+    cert_file: OsString,
+    key_file: OsString,
+    ca_file: OsString,
     url: Url,
+
+    #[clap(flatten)]
+    auth: auth::Args,
+
+    #[clap(flatten)]
+    http: http::Args,
+}
+
+/// A resource that Helm can request from a registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Resource {
+    /// The `index.yaml` of a user or organization.
+    Index { owner: Name },
+
+    /// A chart tarball of a repository's release.
+    Tarball { path: Path, version: String },
+}
+
+impl Resource {
+    fn from_url(url: &Url) -> eyre::Result<Resource> {
+        if url.scheme() != "charted" {
+            bail!(
+                "expected a `charted://` url, received scheme `{}` instead",
+                url.scheme()
+            );
+        }
+
+        let segments = url
+            .path_segments()
+            .map(|segments| segments.filter(|s| !s.is_empty()).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        match segments.as_slice() {
+            [owner, "index.yaml"] => Ok(Resource::Index {
+                owner: owner
+                    .parse()
+                    .map_err(|e| eyre!("invalid owner `{owner}` in url {url}: {e}"))?,
+            }),
+
+            [owner, repo, file] => {
+                let Some(version) = file.strip_suffix(".tgz").or_else(|| file.strip_suffix(".tar.gz")) else {
+                    bail!("expected a `.tgz` chart tarball in url {url}, received `{file}` instead");
+                };
+
+                Ok(Resource::Tarball {
+                    path: format!("{owner}/{repo}")
+                        .parse()
+                        .map_err(|e| eyre!("invalid repository `{owner}/{repo}` in url {url}: {e}"))?,
+                    version: version.to_owned(),
+                })
+            }
+
+            _ => bail!(
+                "unable to map url {url} to a resource, expected either `charted://{{host}}/{{owner}}/index.yaml` or `charted://{{host}}/{{owner}}/{{repo}}/{{version}}.tgz`"
+            ),
+        }
+    }
+
+    /// Returns the API endpoint for this resource, relative to the API version.
+    fn endpoint(&self) -> String {
+        match self {
+            Resource::Index { owner } => format!("indexes/{owner}"),
+            Resource::Tarball { path, version } => format!("repositories/{path}/releases/{version}/tarball"),
+        }
+    }
+}
+
+/// Returns the [`Registry`] that a `charted://` URL points to.
+fn registry_from_url(url: &Url) -> eyre::Result<Registry> {
+    let Some(host) = url.host_str() else {
+        bail!("url {url} doesn't contain a host");
+    };
+
+    let authority = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_owned(),
+    };
+
+    Ok(Registry {
+        version: api::Version::V1,
+        url: Url::parse(&format!("https://{authority}/api/"))?,
+        proxy: None,
+    })
+}
+
+/// Converts an argument from Helm into a path, or `None` if it was empty.
+fn non_empty(arg: OsString) -> Option<PathBuf> {
+    (!arg.is_empty()).then(|| PathBuf::from(arg))
+}
+
+/// Builds the HTTP client for the context, with the client identity and extra
+/// trust roots that Helm passed in.
+fn build_client(
+    http: &http::Args,
+    config: Option<&Config>,
+    registry: &Url,
+    credential: Option<&Credential>,
+    cert_file: Option<PathBuf>,
+    key_file: Option<PathBuf>,
+    ca_file: Option<PathBuf>,
+) -> eyre::Result<http::Client> {
+    let mut builder = http.builder(config, registry, credential)?;
+
+    match (cert_file, key_file) {
+        (Some(cert), key) => builder = builder.identity(http::Identity { cert, key }.load()?),
+        (None, Some(_)) => bail!("a key file was given without a cert file"),
+        (None, None) => {}
+    }
+
+    if let Some(path) = ca_file {
+        builder = http::Certificate::File {
+            path,
+            kind: http::CertKind::Pem,
+        }
+        .apply(builder)?;
+    }
+
+    let client = builder.build().context("failed to build HTTP client")?;
+    Ok(http::Client::new(client, http.retry(config)?))
 }
 
 pub async fn run(
@@ -33,14 +188,140 @@ pub async fn run(
         key_file,
         ca_file,
         url,
+        auth,
+        http,
     }: Args,
+    globals: Globals,
 ) -> eyre::Result<()> {
-    info!(
-        "cert_file={}; key_file={}; ca_file={}; url={url}",
-        cert_file.display(),
-        key_file.display(),
-        ca_file.display()
-    );
+    let resource = Resource::from_url(&url)?;
+    let registry = registry_from_url(&url)?;
+    let endpoint = Url::parse(&registry.join_url(resource.endpoint())?)?;
+
+    debug!(%url, %endpoint, "downloading resource");
+
+    let auth = Auth::load(&auth)?;
+    let credential = auth.credential_for(&endpoint).map(|(context, credential)| {
+        debug!(%context, registry = %credential.registry, "using credentials from context");
+        credential
+    });
+
+    if credential.is_none() {
+        debug!(%endpoint, "no context matches the registry, sending the request without credentials");
+    }
+
+    let config = http.config();
+    let client = build_client(
+        &http,
+        config.as_ref(),
+        &endpoint,
+        credential,
+        non_empty(cert_file),
+        non_empty(key_file),
+        non_empty(ca_file),
+    )?;
+
+    let request = client.get(endpoint.clone());
+
+    // nothing is written to disk when credentials only come from environment variables.
+    let cache = if auth.is_environment_only() {
+        if globals.offline {
+            bail!("`--offline` can't be used when credentials are given through environment variables");
+        }
+
+        Cache::disabled()
+    } else {
+        Cache::open(None, globals.offline)?
+    };
+    let contents = match resource {
+        Resource::Index { .. } => cache.index(&client, &endpoint, request).await?,
+        Resource::Tarball { ref path, ref version } => {
+            let index = Resource::Index {
+                owner: path.owner.clone(),
+            };
+
+            let index = Url::parse(&registry.join_url(index.endpoint())?)?;
+            let digest = cache.digest_of(&index, path.repository.as_ref(), version);
 
+            cache.chart(&client, &endpoint, digest.as_deref(), request).await?
+        }
+    };
+
+    let mut stdout = io::stdout();
+    stdout.write_all(&contents)?;
+    stdout.flush()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Resource, build_client, registry_from_url};
+    use crate::{
+        config::Config,
+        http::{
+            self,
+            testing::{Server, args},
+        },
+    };
+    use url::Url;
+
+    #[test]
+    fn resource_from_url() {
+        let url = Url::parse("charted://charts.noelware.org/noel/index.yaml").unwrap();
+        assert_eq!(
+            Resource::Index {
+                owner: "noel".parse().unwrap()
+            },
+            Resource::from_url(&url).unwrap()
+        );
+
+        let url = Url::parse("charted://charts.noelware.org/noel/ume/1.0.0.tgz").unwrap();
+        assert_eq!(
+            Resource::Tarball {
+                path: "noel/ume".parse().unwrap(),
+                version: String::from("1.0.0")
+            },
+            Resource::from_url(&url).unwrap()
+        );
+
+        assert!(Resource::from_url(&Url::parse("charted://charts.noelware.org/noel").unwrap()).is_err());
+        assert!(Resource::from_url(&Url::parse("https://charts.noelware.org/noel/index.yaml").unwrap()).is_err());
+    }
+
+    #[test]
+    fn endpoints() {
+        let url = Url::parse("charted://localhost:3651/noel/ume/1.0.0.tgz").unwrap();
+        let registry = registry_from_url(&url).unwrap();
+        let resource = Resource::from_url(&url).unwrap();
+
+        assert_eq!(
+            Ok(String::from(
+                "https://localhost:3651/api/v1/repositories/noel/ume/releases/1.0.0/tarball"
+            )),
+            registry.join_url(resource.endpoint())
+        );
+    }
+
+    #[test]
+    fn client_uses_config_proxy() {
+        let server = Server::serve(&["200 Connection Established"]);
+        let config = toml::from_str::<Config>(&format!(
+            "[registry.local]\nurl = \"https://localhost:3651/api\"\nproxy = \"{}\"\n",
+            server.url
+        ))
+        .unwrap();
+
+        let url = Url::parse("charted://localhost:3651/noel/index.yaml").unwrap();
+        let registry = registry_from_url(&url).unwrap();
+        let endpoint = Url::parse(&registry.join_url(Resource::from_url(&url).unwrap().endpoint()).unwrap()).unwrap();
+
+        // the registry is only reachable over TLS, so only the tunnel is checked.
+        let http = http::Args {
+            retry_attempts: Some(1),
+            ..args()
+        };
+
+        let client = build_client(&http, Some(&config), &endpoint, None, None, None, None).unwrap();
+        let _ = smol::block_on(async_compat::Compat::new(client.send(client.get(endpoint))));
+        assert!(server.requests()[0].starts_with("connect localhost:3651 http/1.1\r\n"));
+    }
+}
@@ -14,19 +14,36 @@
 // limitations under the License.
 
 use crate::{
-    auth::{self, Context},
+    Globals,
+    api::{self, ApiKey, Session},
+    auth::{self, Auth, Context, Credential, Repr},
     http,
 };
 use charted_core::serde::Duration;
+use chrono::{TimeDelta, Utc};
+use eyre::Context as _;
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::json;
+use std::{
+    io::{self, BufRead, Write},
+    time::{self, SystemTime, UNIX_EPOCH},
+};
 use url::Url;
 
+mod flow;
+
+/// Host of the official **charted-server** hub, which uses the login flow
+/// by default.
+pub(crate) const OFFICIAL_HUB: &str = "charts.noelware.org";
+
 /// Log into a **charted-server** registry.
 ///
 /// The plugin uses a API key resource instead of basic authentication or
 /// a session token so that it can live as long it requires to. An expiration
 /// date can also be passed in via the `--expire-in` flag. If the plugin sees
 /// the API key expires then it'll remove it from the `auth.yaml` file that
-/// it founds the key from.
+/// it founds the key from, either with `helm charted context prune` or the next
+/// time the file is changed.
 ///
 /// If the registry is Noelware's production server, then it'll use the login flow
 /// system and will construct the API key when the flow is successful (this can be
@@ -46,10 +63,19 @@ pub struct Args {
     #[arg(long)]
     expire_in: Option<Duration>,
 
+    /// scopes that the api key is allowed to use. by default, the server decides.
+    #[arg(long = "scope")]
+    scopes: Vec<String>,
+
     /// disables the login flow for the official **charted-server** hub.
     #[arg(long)]
     no_login_flow: bool,
 
+    /// authorization endpoint to use for the login flow. if this is set, then
+    /// the login flow is used for any registry.
+    #[arg(long, env = "CHARTED_HELM_LOGIN_FLOW_URL", hide = true)]
+    login_flow_url: Option<Url>,
+
     /// marks the successful login as the default context.
     #[arg(long = "make-default")]
     mk_default: bool,
@@ -60,3 +86,182 @@ pub struct Args {
     #[clap(flatten)]
     auth: auth::Args,
 }
+
+pub async fn run(
+    Args {
+        registry,
+        name,
+        expire_in,
+        scopes,
+        no_login_flow,
+        login_flow_url,
+        mk_default,
+        http,
+        auth: auth_args,
+    }: Args,
+    globals: Globals,
+) -> eyre::Result<()> {
+    globals.ensure_online("logging in")?;
+
+    // the files are only locked once the API key was created, so other commands
+    // aren't blocked while the user is logging in.
+    let auth = Auth::load(&auth_args)?;
+    let context = name.unwrap_or_else(|| Context::new(registry.host_str().unwrap_or("default")));
+
+    if auth.credentials.contains_key(&context) {
+        warn!(
+            "context '{}' already exists, it'll be replaced on a successful login",
+            context
+        );
+    }
+
+    // keep the HTTP settings of the context that is being replaced, if any.
+    let settings = auth
+        .credentials
+        .get(&context)
+        .map(|credential| credential.http.clone())
+        .unwrap_or_default();
+
+    let client = http.client(
+        http.config().as_ref(),
+        &registry,
+        Some(&Credential {
+            http: settings.clone(),
+            ..Credential::new(registry.clone(), None)
+        }),
+    )?;
+
+    let use_login_flow = !no_login_flow && (login_flow_url.is_some() || registry.host_str() == Some(OFFICIAL_HUB));
+    let access_token = if use_login_flow {
+        let authorize = match login_flow_url {
+            Some(url) => url,
+            None => Url::parse(flow::DEFAULT_AUTHORIZE_URL)?,
+        };
+
+        info!("logging into registry {} with the login flow", registry);
+        flow::run(
+            &authorize,
+            |url| webbrowser::open(url.as_str()).is_ok(),
+            time::Duration::from_secs(300),
+        )
+        .await?
+    } else {
+        let username = prompt("Username: ")?;
+        let password = SecretString::new(rpassword::prompt_password("Password: ")?.into());
+
+        info!("logging into registry {} as user '{}'", registry, username);
+
+        session(&client, &registry, &username, &password).await?
+    };
+
+    let credential = create_api_key(
+        &client,
+        registry,
+        &access_token,
+        expire_in,
+        &scopes,
+        "API key created by `helm charted login`",
+    )
+    .await?;
+
+    info!(
+        "logged in successfully! saving api key '{}' as context '{}'",
+        credential.api_key.as_deref().unwrap_or_default(),
+        context
+    );
+
+    Auth::load_for_update(&auth_args)?.commit(|me| {
+        me.credentials.insert(context.clone(), Credential {
+            http: settings,
+            ..credential
+        });
+
+        if mk_default {
+            info!("switching from {} ~> {}", me.current, context);
+            me.set_default(context);
+        }
+    })
+}
+
+/// Creates a session for a user with [`POST /users/login`] and returns its
+/// access token.
+///
+/// [`POST /users/login`]: https://charts.noelware.org/docs/server/latest/api/reference/users#POST-/users/login
+pub(crate) async fn session(
+    client: &http::Client,
+    registry: &Url,
+    username: &str,
+    password: &SecretString,
+) -> eyre::Result<SecretString> {
+    let session: Session = api::send(
+        client,
+        client.post(api::endpoint(registry, "users/login")?).json(&json!({
+            "username": username,
+            "password": password.expose_secret(),
+        })),
+    )
+    .await
+    .context("failed to login")?;
+
+    Ok(session.access_token)
+}
+
+/// Creates an API key with a session's access token and returns a [`Credential`]
+/// that uses it.
+pub(crate) async fn create_api_key(
+    client: &http::Client,
+    registry: Url,
+    access_token: &SecretString,
+    expire_in: Option<Duration>,
+    scopes: &[String],
+    description: &str,
+) -> eyre::Result<Credential> {
+    let name = format!(
+        "charted-helm-plugin-{}",
+        SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+    );
+
+    debug!(%name, "creating api key");
+
+    let key: ApiKey = api::send(
+        client,
+        client
+            .put(api::endpoint(&registry, "apikeys")?)
+            .bearer_auth(access_token.expose_secret())
+            .json(&json!({
+                "name": name,
+                "description": description,
+                "expires_in": expire_in,
+                "scopes": scopes,
+            })),
+    )
+    .await
+    .context("failed to create api key")?;
+
+    let Some(token) = key.token else {
+        bail!("server didn't send back the token of api key '{}'", key.name);
+    };
+
+    let expires_at = expire_in
+        .map(|expire_in| TimeDelta::from_std(*expire_in).map(|delta| Utc::now() + delta))
+        .transpose()
+        .context("`--expire-in` is out of range")?;
+
+    Ok(Credential {
+        api_key: Some(key.name),
+        expires_at,
+        ..Credential::new(registry, Some(Repr::ApiKey(token)))
+    })
+}
+
+/// Prompts the user for a line of input from stdin.
+pub(crate) fn prompt(message: &str) -> io::Result<String> {
+    let mut stderr = io::stderr();
+    write!(stderr, "{message}")?;
+    stderr.flush()?;
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+
+    Ok(line.trim().to_owned())
+}
@@ -0,0 +1,241 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use base64::{Engine, engine::general_purpose};
+use eyre::Context;
+use secrecy::SecretString;
+use smol::{
+    Timer, future,
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use std::{collections::HashMap, time::Duration};
+use url::Url;
+
+/// Authorization endpoint of the official **charted-server** hub.
+pub const DEFAULT_AUTHORIZE_URL: &str = "https://charts.noelware.org/login/cli";
+
+/// How long a connection to the loopback listener can stay idle before it is
+/// dropped, so that connections which are opened by the browser ahead of time
+/// don't hold up the callback.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Runs the browser-based login flow and returns the session's access token.
+///
+/// A loopback listener is started and the `authorize` URL is opened with `open`
+/// with the following query parameters:
+///
+/// - `redirect_uri`: the loopback listener's `/callback` endpoint
+/// - `state`: a random string that must be sent back to the callback
+///
+/// Once the user has logged in, the authorization endpoint redirects the browser
+/// to `{redirect_uri}?state={state}&token={access token}`, or with an `error`
+/// query parameter if the login failed. If `open` returns `false`, then the URL
+/// is printed so it can be opened manually.
+pub async fn run<F: FnOnce(&Url) -> bool>(authorize: &Url, open: F, timeout: Duration) -> eyre::Result<SecretString> {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .context("failed to start loopback listener")?;
+
+    let addr = listener.local_addr()?;
+    let state = random_state()?;
+
+    let mut url = authorize.clone();
+    url.query_pairs_mut()
+        .append_pair("redirect_uri", &format!("http://{addr}/callback"))
+        .append_pair("state", &state);
+
+    debug!(%addr, "started loopback listener for login flow");
+    if !open(&url) {
+        eprintln!("Open the following URL in your browser to continue logging in:");
+        eprintln!();
+        eprintln!("    {url}");
+        eprintln!();
+    } else {
+        info!(
+            "opened {} in your browser, waiting for the login flow to complete...",
+            url
+        );
+    }
+
+    let accept = async {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            trace!(%peer, "received connection on loopback listener");
+
+            let handled = future::or(handle(stream, &state), async {
+                Timer::after(CONNECTION_TIMEOUT).await;
+                Err(eyre!("connection was idle for {}s", CONNECTION_TIMEOUT.as_secs()))
+            })
+            .await;
+
+            match handled {
+                Ok(Some(result)) => return result,
+                Ok(None) => {}
+                Err(e) => debug!(%peer, error = %e, "failed to handle connection on loopback listener"),
+            }
+        }
+    };
+
+    let timed_out = async {
+        Timer::after(timeout).await;
+        Err(eyre!("login flow didn't complete within {}s", timeout.as_secs()))
+    };
+
+    future::or(accept, timed_out).await
+}
+
+/// Handles a single request from the browser. Returns `None` if the request
+/// wasn't the callback for this login flow.
+async fn handle(stream: TcpStream, state: &str) -> eyre::Result<Option<eyre::Result<SecretString>>> {
+    let mut reader = BufReader::new(stream.clone());
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        bail!("connection was closed before a request was sent");
+    }
+
+    // we only need the request line, i.e: `GET /callback?... HTTP/1.1`
+    let target = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["GET", target, _] => *target,
+        _ => {
+            respond(stream, "400 Bad Request", "Invalid request.").await;
+            return Ok(None);
+        }
+    };
+
+    let url = Url::parse(&format!("http://localhost{target}"))?;
+    if url.path() != "/callback" {
+        respond(stream, "404 Not Found", "Not found.").await;
+        return Ok(None);
+    }
+
+    let params = url.query_pairs().into_owned().collect::<HashMap<_, _>>();
+    if params.get("state").map(String::as_str) != Some(state) {
+        warn!("received callback with a mismatched state, ignoring");
+        respond(
+            stream,
+            "400 Bad Request",
+            "State mismatch, please try logging in again.",
+        )
+        .await;
+
+        return Ok(None);
+    }
+
+    if let Some(error) = params.get("error") {
+        respond(stream, "200 OK", "Login failed, you can close this tab.").await;
+        return Ok(Some(Err(eyre!("login flow failed: {error}"))));
+    }
+
+    let Some(token) = params.get("token") else {
+        respond(stream, "400 Bad Request", "Missing token.").await;
+        return Ok(Some(Err(eyre!("login flow completed without a token"))));
+    };
+
+    respond(stream, "200 OK", "Logged in successfully! You can close this tab.").await;
+    Ok(Some(Ok(SecretString::new(token.as_str().into()))))
+}
+
+/// Writes a response back to the browser. Failures are only logged, as the
+/// outcome of the login flow doesn't depend on the browser receiving it.
+async fn respond(mut stream: TcpStream, status: &str, message: &str) {
+    let body = format!("<!DOCTYPE html><html><body><p>{message}</p></body></html>");
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    if let Err(e) = async {
+        stream.write_all(response.as_bytes()).await?;
+        stream.flush().await
+    }
+    .await
+    {
+        debug!(error = %e, "failed to respond to the browser");
+    }
+}
+
+fn random_state() -> eyre::Result<String> {
+    let mut buf = [0u8; 32];
+    getrandom::fill(&mut buf).map_err(|e| eyre!("failed to generate random state: {e}"))?;
+
+    Ok(general_purpose::URL_SAFE_NO_PAD.encode(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret;
+    use std::{
+        collections::HashMap,
+        io::{Read, Write},
+        net::TcpStream,
+        thread,
+        time::Duration,
+    };
+    use url::Url;
+
+    // stand-in for the authorization endpoint, which redirects the "browser" back
+    // into the callback with the given query parameters.
+    fn authorize(url: &Url, params: &'static [(&'static str, &'static str)]) -> bool {
+        let query = url.query_pairs().into_owned().collect::<HashMap<_, _>>();
+        let mut callback = Url::parse(&query["redirect_uri"]).unwrap();
+        callback.query_pairs_mut().append_pair("state", &query["state"]);
+        callback.query_pairs_mut().extend_pairs(params);
+
+        thread::spawn(move || {
+            let addrs = callback.socket_addrs(|| None).unwrap();
+
+            // browsers open connections ahead of time which are left idle or
+            // are closed without sending anything.
+            let _idle = TcpStream::connect(&*addrs).unwrap();
+            drop(TcpStream::connect(&*addrs).unwrap());
+
+            let mut stream = TcpStream::connect(&*addrs).unwrap();
+            write!(
+                stream,
+                "GET {}?{} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+                callback.path(),
+                callback.query().unwrap()
+            )
+            .unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+        });
+
+        true
+    }
+
+    #[test]
+    fn login_flow() {
+        let url = Url::parse("http://127.0.0.1/login/cli").unwrap();
+        let token = smol::block_on(super::run(
+            &url,
+            |url| authorize(url, &[("token", "weow")]),
+            Duration::from_secs(10),
+        ))
+        .unwrap();
+
+        assert_eq!("weow", token.expose_secret());
+
+        let result = smol::block_on(super::run(
+            &url,
+            |url| authorize(url, &[("error", "access denied")]),
+            Duration::from_secs(10),
+        ));
+
+        assert!(result.is_err());
+    }
+}
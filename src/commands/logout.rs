@@ -14,20 +14,130 @@
 // limitations under the License.
 
 use crate::{
-    auth::{self, Context},
+    Globals, api,
+    auth::{self, Auth, Context, Repr},
     http,
 };
+use eyre::Context as _;
+use std::process::exit;
 use url::Url;
 
 /// Logs out of a registry once created by `helm charted login`.
+///
+/// The API key is revoked on the registry before the context is removed from
+/// the `auth.yaml` file. If the registry is unreachable, `--local-only` will only
+/// remove the context.
 #[derive(Debug, clap::Parser)]
 pub struct Args {
     registry: Url,
     context: Option<Context>,
 
+    /// only removes the context from the `auth.yaml` file without revoking the
+    /// api key on the registry.
+    #[arg(long)]
+    local_only: bool,
+
     #[clap(flatten)]
     auth: auth::Args,
 
     #[clap(flatten)]
     http: http::Args,
 }
+
+pub async fn run(
+    Args {
+        registry,
+        context,
+        local_only,
+        auth: auth_args,
+        http,
+    }: Args,
+    globals: Globals,
+) -> eyre::Result<()> {
+    if !local_only {
+        globals
+            .ensure_online("revoking the api key")
+            .context("pass `--local-only` to only remove the context")?;
+    }
+
+    // the files are only locked once the API key was revoked.
+    let auth = Auth::load(&auth_args)?;
+    let context = match context {
+        Some(context) => context,
+        None => {
+            let mut matches = auth
+                .credentials
+                .iter()
+                .filter(|(_, credential)| credential.matches(&registry))
+                .map(|(context, _)| context.clone())
+                .collect::<Vec<_>>();
+
+            match matches.len() {
+                0 => {
+                    error!("no context is logged into registry {}", registry);
+                    exit(1);
+                }
+
+                1 => matches.remove(0),
+                _ => {
+                    matches.sort();
+
+                    error!(
+                        "multiple contexts are logged into registry {}: {}",
+                        registry,
+                        matches.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+                    );
+
+                    info!(
+                        "tip: pass in the context to log out of: `helm charted logout {} <context>`",
+                        registry
+                    );
+                    exit(1);
+                }
+            }
+        }
+    };
+
+    let Some(credential) = auth.credentials.get(&context) else {
+        error!("context '{}' doesn't exist!", context);
+        exit(1);
+    };
+
+    if !credential.matches(&registry) {
+        error!(
+            "context '{}' points to registry {}, not {}",
+            context, credential.registry, registry
+        );
+
+        exit(1);
+    }
+
+    if local_only {
+        warn!("--local-only was passed in, the api key will not be revoked on the registry");
+    } else if let (Some(Repr::ApiKey(_) | Repr::EnvironmentVariable(_) | Repr::Helper { .. }), Some(name)) =
+        (&credential.repr, &credential.api_key)
+    {
+        let client = http.client(http.config().as_ref(), &credential.registry, Some(credential))?;
+
+        info!("revoking api key '{}' on registry {}", name, credential.registry);
+        api::send_empty(
+            &client,
+            client.delete(api::endpoint(&credential.registry, &format!("apikeys/{name}"))?),
+        )
+        .await
+        .with_context(|| {
+            format!("failed to revoke api key '{name}', use `--local-only` to only remove context '{context}'")
+        })?;
+    } else {
+        warn!(
+            "context '{}' doesn't have an api key that was created by `helm charted login`, it'll only be removed",
+            context
+        );
+    }
+
+    info!("removing context '{}'", context);
+
+    let mut auth = Auth::load_for_update(&auth_args)?;
+    auth.remove(&context);
+    auth.save()
+}
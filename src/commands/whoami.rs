@@ -0,0 +1,231 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    Globals,
+    api::{self, ApiKey, User},
+    auth::{self, Auth, Context, Credential},
+    http,
+};
+use chrono::{DateTime, Utc};
+use eyre::Context as _;
+use serde_json::json;
+use std::process::exit;
+
+/// Exit code when the registry rejected the context's credentials, or if the
+/// context doesn't have any.
+const EXIT_UNAUTHENTICATED: i32 = 3;
+
+/// Verifies that a context's credentials are still valid and prints who they
+/// belong to.
+///
+/// This will exit with code **3** if the registry rejected the credentials.
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// Context to verify. Defaults to the current context.
+    context: Option<Context>,
+
+    /// reports as JSON instead of plain text
+    #[arg(short = 'j', long, default_value_t = false)]
+    json: bool,
+
+    #[clap(flatten)]
+    auth: auth::Args,
+
+    #[clap(flatten)]
+    http: http::Args,
+}
+
+/// Result of verifying a context's credentials.
+enum Verified {
+    /// The context doesn't have any credentials, or the registry rejected them.
+    Unauthenticated,
+
+    /// The user that the credentials belong to and the API key that was used.
+    User(User, Option<ApiKey>),
+}
+
+pub async fn run(
+    Args {
+        context,
+        json,
+        auth,
+        http,
+    }: Args,
+    globals: Globals,
+) -> eyre::Result<()> {
+    globals.ensure_online("verifying a context")?;
+
+    let auth = Auth::load(&auth)?;
+    let context = context.unwrap_or_else(|| auth.current.clone());
+
+    let Some(credential) = auth.credentials.get(&context) else {
+        error!("context '{}' doesn't exist!", context);
+        exit(1);
+    };
+
+    let client = http.client(http.config().as_ref(), &credential.registry, Some(credential))?;
+    let (user, key) = match verify(&client, &context, credential).await? {
+        Verified::User(user, key) => (user, key),
+        Verified::Unauthenticated => exit(EXIT_UNAUTHENTICATED),
+    };
+
+    let expires_at = key.as_ref().and_then(|key| key.expires_at).or(credential.expires_at);
+
+    if json {
+        let data = json!({
+            "context": context,
+            "registry": credential.registry,
+            "user": user,
+            "api_key": key.as_ref().map(|key| json!({
+                "name": key.name,
+                "scopes": key.scopes,
+                "expires_at": expires_at,
+            })),
+        });
+
+        println!("{}", serde_json::to_string_pretty(&data)?);
+        return Ok(());
+    }
+
+    print!("{}", render(&context, credential, &user, key.as_ref(), expires_at));
+    Ok(())
+}
+
+async fn verify(client: &http::Client, context: &Context, credential: &Credential) -> eyre::Result<Verified> {
+    if credential.repr.is_none() {
+        error!("context '{}' doesn't have any credentials", context);
+        return Ok(Verified::Unauthenticated);
+    }
+
+    let user = api::send::<User>(client, client.get(api::endpoint(&credential.registry, "users/@me")?)).await;
+    let user = match user {
+        Ok(user) => user,
+        Err(e) => match e.downcast_ref::<api::Failure>() {
+            Some(failure) if failure.is_unauthorized() => {
+                error!(
+                    "registry {} rejected the credentials of context '{}': {}",
+                    credential.registry, context, failure
+                );
+
+                return Ok(Verified::Unauthenticated);
+            }
+
+            _ => return Err(e),
+        },
+    };
+
+    let key = match credential.api_key {
+        Some(ref name) => Some(
+            api::send::<ApiKey>(
+                client,
+                client.get(api::endpoint(&credential.registry, &format!("apikeys/{name}"))?),
+            )
+            .await
+            .with_context(|| format!("failed to fetch api key '{name}'"))?,
+        ),
+
+        None => None,
+    };
+
+    Ok(Verified::User(user, key))
+}
+
+fn render(
+    context: &Context,
+    credential: &Credential,
+    user: &User,
+    key: Option<&ApiKey>,
+    expires_at: Option<DateTime<Utc>>,
+) -> String {
+    let mut out = match user.name {
+        Some(ref name) => format!("Logged in as {} ({})\n", user.username, name),
+        None => format!("Logged in as {}\n", user.username),
+    };
+
+    out.push_str(&format!("~> Context:  {context}\n"));
+    out.push_str(&format!("~> Registry: {}\n", credential.registry));
+
+    if let Some(key) = key {
+        let expiry = match expires_at {
+            Some(at) => at.to_string(),
+            None => String::from("never"),
+        };
+
+        out.push_str(&format!("~> API Key:  {}\n", key.name));
+        out.push_str(&format!("~> Expires:  {expiry}\n"));
+        out.push_str(&format!("~> Scopes:   {}\n", key.scopes));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Args, Verified, render, run, verify};
+    use crate::{
+        Globals,
+        api::{ApiKey, User},
+        auth::{Context, Credential, Repr},
+        http::{Client, Retry, testing::Server},
+    };
+    use clap::Parser;
+
+    #[test]
+    fn offline() {
+        let args = Args::try_parse_from(["whoami", "--auth-file", "/nonexistent/auth.yaml"]).unwrap();
+        let error = smol::block_on(run(args, Globals { offline: true })).unwrap_err();
+        assert!(error.to_string().starts_with("running in offline mode"));
+    }
+
+    #[test]
+    fn unauthenticated() {
+        let server = Server::serve(&["401 Unauthorized"]);
+        let client = Client::new(reqwest::Client::new(), Retry::default());
+        let context = Context::new("default");
+
+        // without any credentials, the registry is never asked.
+        let credential = Credential::new(server.url.clone(), None);
+        let verified = smol::block_on(async_compat::Compat::new(verify(&client, &context, &credential))).unwrap();
+        assert!(matches!(verified, Verified::Unauthenticated));
+        assert!(server.requests().is_empty());
+
+        let credential = Credential::new(server.url.clone(), Some(Repr::ApiKey(String::from("weow").into())));
+        let verified = smol::block_on(async_compat::Compat::new(verify(&client, &context, &credential))).unwrap();
+        assert!(matches!(verified, Verified::Unauthenticated));
+        assert!(server.requests()[0].starts_with("get /users/@me "));
+    }
+
+    #[test]
+    fn render_expiry() {
+        let context = Context::new("default");
+        let credential = Credential::new("https://charts.noelware.org/api/v1".parse().unwrap(), None);
+        let user = User {
+            username: String::from("noel"),
+            name: None,
+        };
+
+        let key =
+            serde_json::from_str::<ApiKey>(r#"{"name":"helm","scopes":"read","expires_in":"2030-01-01T00:00:00Z"}"#)
+                .unwrap();
+
+        let rendered = render(&context, &credential, &user, Some(&key), key.expires_at);
+        assert!(rendered.contains("~> API Key:  helm\n"));
+        assert!(rendered.contains("~> Expires:  2030-01-01 00:00:00 UTC\n"));
+
+        let rendered = render(&context, &credential, &user, Some(&key), None);
+        assert!(rendered.contains("~> Expires:  never\n"));
+    }
+}
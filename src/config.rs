@@ -18,19 +18,45 @@ use charted_types::name::{self, Name};
 use eyre::Context;
 use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{
-    collections::BTreeMap,
-    fs::{self, OpenOptions},
-    io::Write,
-    path::PathBuf,
-    str::FromStr,
-};
+use std::{collections::BTreeMap, fs, path::PathBuf, str::FromStr};
 use url::Url;
 
 pub mod global;
 pub mod registry;
 pub mod repository;
 
+/// Locations in the working directory that a `.charted.toml` file is looked up
+/// from, in order.
+const DEFAULT_LOCATIONS: [&str; 2] = ["./.charted.toml", "./charted.toml"];
+
+/// Names of the configuration file, in the order that they're looked up in a
+/// single directory.
+const FILE_NAMES: [&str; 2] = [".charted.toml", "charted.toml"];
+
+/// Returns the closest `.charted.toml` (or `charted.toml`) file by walking upwards
+/// from `start`.
+pub fn find(start: &std::path::Path) -> eyre::Result<Option<PathBuf>> {
+    for dir in start.ancestors() {
+        if let Some(path) = find_in(dir)? {
+            return Ok(Some(path));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the `.charted.toml` (or `charted.toml`) file that lives in `dir`.
+pub fn find_in(dir: &std::path::Path) -> eyre::Result<Option<PathBuf>> {
+    for name in FILE_NAMES {
+        let path = dir.join(name);
+        if path.try_exists()? {
+            return Ok(Some(path));
+        }
+    }
+
+    Ok(None)
+}
+
 /// A repository path that is joined from the first [`Name`], which is the
 /// owner of the repository and the secondary [`Name`], which is the repository
 /// name.
@@ -195,6 +221,7 @@ impl Config {
             config.registries.insert(String::from("default"), registry::Registry {
                 version: api::Version::V1,
                 url: Url::parse("https://charts.noelware.org/api").unwrap(),
+                proxy: None,
             });
         }
 
@@ -202,16 +229,30 @@ impl Config {
         Ok(config)
     }
 
+    /// Loads the closest configuration file by walking upwards from `start`, or
+    /// returns `None` if there isn't one.
+    pub fn discover(start: &std::path::Path) -> eyre::Result<Option<Self>> {
+        match find(start)? {
+            Some(path) => Config::load(path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the proxy of the first registry that points to the same host as
+    /// `url` and has one configured.
+    pub fn proxy_for(&self, url: &Url) -> Option<&crate::http::Proxy> {
+        self.registries
+            .values()
+            .filter(|registry| registry.matches_host(url))
+            .find_map(|registry| registry.proxy.as_ref())
+    }
+
     pub fn flush_and_save(&self) -> eyre::Result<()> {
         debug!(path = %self.opened_from.display(), "saving and flushing changes");
 
-        let mut file = OpenOptions::new().write(true).open(&self.opened_from)?;
         let serialized = toml::to_string_pretty(self)?;
-
-        write!(file, "{serialized}")?;
-        file.flush()?;
-
-        Ok(())
+        let _lock = crate::fs::lock(&self.opened_from)?;
+        crate::fs::write_atomic(&self.opened_from, serialized.as_bytes(), None)
     }
 
     fn get_potential_default_path<P: Into<Option<PathBuf>>>(potential: P) -> eyre::Result<PathBuf> {
@@ -219,7 +260,7 @@ impl Config {
             return Ok(path);
         }
 
-        for p in [std::path::Path::new("./.charted.toml"), std::path::Path::new("./charted.toml")] {
+        for p in DEFAULT_LOCATIONS.map(std::path::Path::new) {
             trace!(potential = %p.display(), "checking if path exists");
 
             if p.try_exists()? {
@@ -241,3 +282,50 @@ pub struct Args {
     #[arg(short = 'c', long, env = "CHARTED_HELM_TOML_FILE")]
     pub config: Option<PathBuf>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use std::fs;
+
+    #[test]
+    fn discover_upwards() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("charts/hello");
+        fs::create_dir_all(&nested).unwrap();
+
+        assert!(Config::discover(&nested).unwrap().is_none());
+
+        fs::write(dir.path().join("charted.toml"), "[global.retry]\nattempts = 5\n").unwrap();
+        let config = Config::discover(&nested).unwrap().unwrap();
+        assert_eq!(Some(5), config.global.retry.map(|retry| retry.attempts));
+
+        // `.charted.toml` is preferred over `charted.toml` in the same directory.
+        fs::write(dir.path().join(".charted.toml"), "[global").unwrap();
+        assert!(Config::discover(&nested).is_err());
+    }
+
+    #[test]
+    fn save_waits_for_lock() {
+        use std::{sync::mpsc, thread, time::Duration};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".charted.toml");
+        fs::write(&path, "[global.retry]\nattempts = 5\n").unwrap();
+
+        let mut config = Config::load(path.clone()).unwrap();
+        config.global.retry = None;
+
+        let lock = crate::fs::lock(&path).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let handle = thread::spawn(move || tx.send(config.flush_and_save().is_ok()).unwrap());
+
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+        assert!(fs::read_to_string(&path).unwrap().contains("attempts = 5"));
+
+        drop(lock);
+        assert!(rx.recv().unwrap());
+        handle.join().unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("attempts"));
+    }
+}
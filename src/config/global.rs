@@ -46,6 +46,18 @@ pub struct Global {
         skip_serializing_if = "VersionReq::is_wildcard"
     )]
     pub helm_constraint: VersionReq,
+
+    /// Name of the authentication context to use for commands that are run
+    /// within this project, which overrides the current context from the
+    /// `auth.yaml` files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+
+    /// Policy for retrying requests to a registry that failed because of a flaky
+    /// network or an overloaded registry. The `--retry-*` flags take precedence
+    /// over it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<crate::http::Retry>,
 }
 
 impl Default for Global {
@@ -53,6 +65,8 @@ impl Default for Global {
         Global {
             plugin_constraint: __default_plugin_constraint(),
             helm_constraint: __default_helm_constraint(),
+            context: None,
+            retry: None,
         }
     }
 }
@@ -13,6 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::http::Proxy;
 use charted_core::api::Version;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -29,6 +30,13 @@ pub struct Registry {
     /// URL of the registry to point to. This doesn't include the API version
     /// in the URI itself (i.e, `https://charts.noelware.org/api/v1`).
     pub url: Url,
+
+    /// Proxy to send requests to this registry through, or `direct` to connect
+    /// to it directly. This takes precedence over the `--proxy` flag and the
+    /// `HTTPS_PROXY` and `NO_PROXY` environment variables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub proxy: Option<Proxy>,
 }
 
 impl Registry {
@@ -40,6 +48,13 @@ impl Registry {
             .join(&format!("{}/{input}", self.version))
             .map(|x| x.to_string())
     }
+
+    /// Returns `true` if `url` points to the same host as this registry.
+    pub fn matches_host(&self, url: &Url) -> bool {
+        self.url.scheme() == url.scheme() &&
+            self.url.host_str() == url.host_str() &&
+            self.url.port_or_known_default() == url.port_or_known_default()
+    }
 }
 
 impl Display for Registry {
@@ -65,6 +80,7 @@ mod tests {
         let registry = Registry {
             version: Version::default(),
             url: Url::parse("https://charts.noelware.org").expect("invalid url"),
+            proxy: None,
         };
 
         assert_eq!(
@@ -72,4 +88,18 @@ mod tests {
             registry.join_url("weow/fluff")
         );
     }
+
+    #[test]
+    fn matches_host() {
+        let registry = Registry {
+            version: Version::default(),
+            url: Url::parse("https://charts.noelware.org/api").expect("invalid url"),
+            proxy: Some("direct".parse().expect("invalid proxy")),
+        };
+
+        assert!(registry.matches_host(&Url::parse("https://charts.noelware.org/api/v1/indexes/noel").unwrap()));
+        assert!(registry.matches_host(&Url::parse("https://charts.noelware.org:443").unwrap()));
+        assert!(!registry.matches_host(&Url::parse("http://charts.noelware.org").unwrap()));
+        assert!(!registry.matches_host(&Url::parse("https://charts.internal").unwrap()));
+    }
 }
@@ -0,0 +1,192 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Filesystem helpers for writing the `auth.yaml` and `.charted.toml` files.
+
+use eyre::Context;
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Atomically replaces the contents of `path` with `contents`.
+///
+/// The contents are written into a temporary file in the same directory, which is
+/// synced to disk and then renamed over `path`, so a crash or a concurrent
+/// `helm charted` process can never leave a half-written file behind. If `path` is
+/// a symbolic link, then the file it points to is replaced instead.
+///
+/// This doesn't prevent concurrent processes from overwriting each other's changes,
+/// use [`lock`] for the whole time that a file is read, modified and written back.
+///
+/// If `mode` is given, the file is created with those permissions on Unix.
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub fn write_atomic(path: &Path, contents: &[u8], mode: Option<u32>) -> eyre::Result<()> {
+    let resolved;
+    let path = if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        resolved = fs::canonicalize(path).with_context(|| format!("failed to resolve symlink {}", path.display()))?;
+        trace!(path = %path.display(), target = %resolved.display(), "writing through symlink");
+
+        resolved.as_path()
+    } else {
+        path
+    };
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    fs::create_dir_all(parent)?;
+
+    let mut prefix = OsString::from(".");
+    prefix.push(path.file_name().unwrap_or_default());
+
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(&prefix);
+
+    #[cfg(unix)]
+    builder.permissions(permissions(path, mode));
+
+    let mut file = builder
+        .tempfile_in(parent)
+        .with_context(|| format!("failed to create temporary file in {}", parent.display()))?;
+
+    file.write_all(contents)?;
+    file.as_file().sync_all()?;
+    file.persist(path)
+        .with_context(|| format!("failed to replace file {}", path.display()))?;
+
+    // sync the directory as well, so that the rename itself is durable.
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
+/// Returns the permissions that `path` should be written with, which is `mode`
+/// or the permissions of the file that is being replaced.
+#[cfg(unix)]
+fn permissions(path: &Path, mode: Option<u32>) -> fs::Permissions {
+    use std::os::unix::fs::PermissionsExt;
+
+    match mode {
+        Some(mode) => fs::Permissions::from_mode(mode),
+        None => fs::metadata(path)
+            .map(|metadata| metadata.permissions())
+            .unwrap_or_else(|_| fs::Permissions::from_mode(0o644)),
+    }
+}
+
+/// Warns if `path` can be read or written by anyone other than its owner.
+#[cfg(unix)]
+pub fn warn_if_loose_permissions(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Ok(metadata) = fs::metadata(path) {
+        let mode = metadata.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            warn!(
+                "file {} has permissions {:o} which allows other users to read it, consider running `chmod 600 {}`",
+                path.display(),
+                mode,
+                path.display()
+            );
+        }
+    }
+}
+
+/// Warns if `path` can be read or written by anyone other than its owner.
+#[cfg(not(unix))]
+pub fn warn_if_loose_permissions(_: &Path) {}
+
+/// Acquires an exclusive advisory lock on `<path>.lock`, which is released once
+/// the returned file is dropped.
+pub fn lock(path: &Path) -> eyre::Result<File> {
+    let mut lock = OsString::from(path.as_os_str());
+    lock.push(".lock");
+
+    let lock = PathBuf::from(lock);
+    if let Some(parent) = lock.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock)
+        .with_context(|| format!("failed to open lock file {}", lock.display()))?;
+
+    trace!(path = %lock.display(), "acquiring lock");
+
+    // called through the trait since `File::lock` is still unstable on our toolchain.
+    fs4::FileExt::lock(&file).with_context(|| format!("failed to acquire lock on {}", lock.display()))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lock, write_atomic};
+    use std::fs;
+
+    #[test]
+    fn write_atomic_replaces_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("auth.yaml");
+
+        write_atomic(&path, b"current: default", Some(0o600)).unwrap();
+        write_atomic(&path, b"current: other", Some(0o600)).unwrap();
+
+        assert_eq!("current: other", fs::read_to_string(&path).unwrap());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(0o600, fs::metadata(&path).unwrap().permissions().mode() & 0o777);
+        }
+
+        // no temporary files should be left behind.
+        assert_eq!(1, fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[test]
+    fn write_atomic_while_locked() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("auth.yaml");
+
+        let _lock = lock(&path).unwrap();
+        write_atomic(&path, b"current: default", None).unwrap();
+        assert_eq!("current: default", fs::read_to_string(&path).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomic_through_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let (target, link) = (dir.path().join("dotfiles-auth.yaml"), dir.path().join("auth.yaml"));
+
+        fs::write(&target, "current: default").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomic(&link, b"current: other", None).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!("current: other", fs::read_to_string(&target).unwrap());
+    }
+}
@@ -0,0 +1,256 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use charted_core::serde::Duration;
+use chrono::{DateTime, Utc};
+use reqwest::{
+    Method, RequestBuilder, Response, StatusCode,
+    header::{HeaderValue, RETRY_AFTER},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time;
+
+/// A policy for retrying requests that failed because of a flaky network or an
+/// overloaded registry.
+///
+/// Only idempotent requests (`GET`, `HEAD`, `DELETE` and `OPTIONS`) are retried,
+/// unless the request is explicitly marked as safe to retry. **charted-server**
+/// uses `PUT` to create resources, so they aren't retried by default. The delay
+/// doubles after each retry, unless the registry replies with a `Retry-After`
+/// header on **429 Too Many Requests** or **503 Service Unavailable**.
+///
+/// ## Example
+/// ```toml
+/// [global.retry]
+/// attempts = 5
+/// delay = "1s"
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Retry {
+    /// Maximum amount of attempts for a request, including the first one. `1`
+    /// disables retries.
+    pub attempts: u32,
+
+    /// Delay before the first retry.
+    pub delay: Duration,
+
+    /// Upper bound of the delay between two attempts, which also applies to
+    /// the registry's `Retry-After` header.
+    pub max_delay: Duration,
+
+    /// Randomizes each delay between half of it and the whole delay, so that
+    /// clients that failed at the same time don't retry in lockstep.
+    pub jitter: bool,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry {
+            attempts: 3,
+            delay: time::Duration::from_millis(500).into(),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl Retry {
+    /// Sends `request` and retries it with this policy. If `retryable` is `false`,
+    /// then only idempotent requests are retried.
+    pub async fn send(&self, request: RequestBuilder, retryable: bool) -> reqwest::Result<Response> {
+        let (client, request) = request.build_split();
+        let request = request?;
+        let retryable = retryable || is_idempotent(request.method());
+
+        let mut attempt = 1;
+        loop {
+            // requests with a streaming body can't be cloned, so they're only
+            // sent once.
+            let Some(next) = (retryable && attempt < self.attempts)
+                .then(|| request.try_clone())
+                .flatten()
+            else {
+                return client.execute(request).await;
+            };
+
+            let delay = match client.execute(next).await {
+                Ok(response) if is_transient(response.status()) => {
+                    let delay = match response.status() {
+                        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+                            response.headers().get(RETRY_AFTER).and_then(retry_after)
+                        }
+
+                        _ => None,
+                    }
+                    .unwrap_or_else(|| self.backoff(attempt))
+                    .min(*self.max_delay);
+
+                    warn!(
+                        url = %request.url(),
+                        attempt,
+                        "registry replied with {}, retrying in {:?}",
+                        response.status(),
+                        delay
+                    );
+
+                    delay
+                }
+
+                Ok(response) => return Ok(response),
+                Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => {
+                    let delay = self.backoff(attempt);
+                    warn!(url = %request.url(), attempt, error = %e, "request failed, retrying in {:?}", delay);
+
+                    delay
+                }
+
+                Err(e) => return Err(e),
+            };
+
+            smol::Timer::after(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Returns the delay before retrying after the given `attempt`, starting at 1.
+    pub fn backoff(&self, attempt: u32) -> time::Duration {
+        let delay = self
+            .delay
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(*self.max_delay);
+
+        if self.jitter {
+            delay.mul_f64(0.5 + fastrand::f64() / 2.0)
+        } else {
+            delay
+        }
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::DELETE | Method::OPTIONS)
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS |
+            StatusCode::BAD_GATEWAY |
+            StatusCode::SERVICE_UNAVAILABLE |
+            StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Parses a `Retry-After` header, which is either an amount of seconds or a
+/// HTTP date.
+fn retry_after(value: &HeaderValue) -> Option<time::Duration> {
+    let value = value.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(time::Duration::from_secs(secs));
+    }
+
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((at - Utc::now()).to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Retry, retry_after};
+    use crate::http::testing::Server;
+    use reqwest::header::HeaderValue;
+    use std::time;
+
+    fn policy(attempts: u32) -> Retry {
+        Retry {
+            attempts,
+            delay: time::Duration::from_millis(1).into(),
+            jitter: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn retries_transient_failures() {
+        let server = Server::serve(&["503 Service Unavailable\r\nretry-after: 0", "502 Bad Gateway", "200 OK"]);
+        let client = reqwest::Client::new();
+
+        let response = smol::block_on(async_compat::Compat::new(
+            policy(3).send(client.get(server.url.clone()), false),
+        ))
+        .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+        assert_eq!(3, server.requests().len());
+
+        let server = Server::serve(&["503 Service Unavailable", "503 Service Unavailable", "200 OK"]);
+        let response = smol::block_on(async_compat::Compat::new(
+            policy(2).send(client.get(server.url.clone()), false),
+        ))
+        .unwrap();
+
+        assert_eq!(503, response.status().as_u16());
+        assert_eq!(2, server.requests().len());
+    }
+
+    #[test]
+    fn only_retries_idempotent_requests() {
+        let server = Server::serve(&["429 Too Many Requests", "200 OK", "200 OK"]);
+        let client = reqwest::Client::new();
+
+        let response = smol::block_on(async_compat::Compat::new(
+            policy(3).send(client.post(server.url.clone()), false),
+        ))
+        .unwrap();
+
+        assert_eq!(429, response.status().as_u16());
+        assert_eq!(1, server.requests().len());
+
+        let response = smol::block_on(async_compat::Compat::new(
+            policy(3).send(client.post(server.url.clone()), true),
+        ))
+        .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+        assert_eq!(2, server.requests().len());
+    }
+
+    #[test]
+    fn backoff_and_retry_after() {
+        let retry = Retry {
+            delay: time::Duration::from_secs(1).into(),
+            max_delay: time::Duration::from_secs(5).into(),
+            jitter: false,
+            ..Default::default()
+        };
+
+        assert_eq!(time::Duration::from_secs(1), retry.backoff(1));
+        assert_eq!(time::Duration::from_secs(4), retry.backoff(3));
+        assert_eq!(time::Duration::from_secs(5), retry.backoff(10));
+
+        assert_eq!(
+            Some(time::Duration::from_secs(120)),
+            retry_after(&HeaderValue::from_static("120"))
+        );
+
+        assert_eq!(
+            Some(time::Duration::ZERO),
+            retry_after(&HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"))
+        );
+
+        assert_eq!(None, retry_after(&HeaderValue::from_static("soon")));
+    }
+}
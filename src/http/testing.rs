@@ -0,0 +1,98 @@
+// 🐻‍❄️🌺 charted-helm-plugin: Helm plugin to help faciliate operations with charted-server
+// Copyright 2023-2025 Noelware, LLC. <team@noelware.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stand-in for a registry (or a proxy) that is used by the tests.
+
+use super::Args;
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+};
+use url::Url;
+
+/// A server that replies to each connection with the next response that it was
+/// given and records the requests that it received.
+pub(crate) struct Server {
+    pub(crate) url: Url,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl Server {
+    /// Serves `responses` in order. Each response is written after `HTTP/1.1 ` and
+    /// is made of the status line and headers, optionally followed by a blank line
+    /// and the body.
+    pub(crate) fn serve(responses: &[&str]) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let responses = responses
+            .iter()
+            .map(|response| response.to_string())
+            .collect::<Vec<_>>();
+
+        let received = requests.clone();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+
+                    request.extend_from_slice(&buf[..n]);
+                }
+
+                received
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request).to_lowercase());
+
+                let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {head}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+            }
+        });
+
+        Server { url, requests }
+    }
+
+    /// Returns the requests that were received so far, in lowercase.
+    pub(crate) fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Returns [`Args`] as if no flags were passed in.
+pub(crate) fn args() -> Args {
+    Args {
+        connect_timeout: None,
+        certificates: Vec::new(),
+        http2_prior_knowledge: false,
+        retry_attempts: None,
+        retry_delay: None,
+        no_retry_jitter: false,
+        proxy: None,
+        no_proxy: None,
+    }
+}
@@ -19,9 +19,12 @@ extern crate tracing;
 #[macro_use]
 extern crate eyre;
 
+pub(crate) mod api;
 pub mod auth;
+pub mod cache;
 pub mod commands;
 pub mod config;
+pub(crate) mod fs;
 pub mod http;
 pub(crate) mod serde;
 
@@ -49,11 +52,40 @@ pub struct Program {
     #[arg(long, short = 'd', env = "CHARTED_HELM_WORKING_DIRECTORY", global = true, default_value = None)]
     pub workdir: Option<PathBuf>,
 
+    /// only serves charts and `index.yaml` files from the local cache and never
+    /// sends requests to a registry. Commands that need a registry will fail.
+    #[arg(long, env = "CHARTED_HELM_OFFLINE", global = true)]
+    pub offline: bool,
+
     #[command(subcommand)]
     pub command: Subcommand,
 }
 
+/// Global options that are passed down into each subcommand.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Globals {
+    /// Whether if `--offline` was passed in.
+    pub offline: bool,
+}
+
+impl Globals {
+    /// Fails if `--offline` was passed in, for commands that can't do anything
+    /// without contacting a registry. `action` describes what needs the registry.
+    pub fn ensure_online(&self, action: &str) -> eyre::Result<()> {
+        if self.offline {
+            bail!("running in offline mode, but {action} requires contacting the registry");
+        }
+
+        Ok(())
+    }
+}
+
 impl Program {
+    /// Returns the [`Globals`] that were configured for this program.
+    pub fn globals(&self) -> Globals {
+        Globals { offline: self.offline }
+    }
+
     #[doc(hidden)]
     pub fn init_logging(&self) {
         tracing_subscriber::registry()
@@ -69,3 +101,14 @@ impl Program {
             .init();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Program;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_cli() {
+        Program::command().debug_assert();
+    }
+}